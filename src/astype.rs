@@ -11,6 +11,7 @@ pub enum ASType {
     I16,
     I32,
     I64,
+    Bool,
     Char,
     Usize,
     F32,
@@ -36,6 +37,7 @@ impl fmt::Display for ASType {
             ASType::I16 => write!(f, "i16"),
             ASType::I32 => write!(f, "i32"),
             ASType::I64 => write!(f, "i64"),
+            ASType::Bool => write!(f, "bool"),
             ASType::Char => write!(f, "char"),
             ASType::Usize => write!(f, "usize"),
             ASType::F32 => write!(f, "f32"),
@@ -54,33 +56,15 @@ impl fmt::Display for ASType {
 }
 
 impl ASType {
-    pub fn is_nullable(&self) -> bool {
+    /// Size of a value of that type in linear memory, unless it is an alias
+    pub fn mem_size(&self) -> Option<usize> {
         match self {
-            ASType::Ptr(_)
-            | ASType::MutPtr(_)
-            | ASType::Record(_)
-            | ASType::Variant(_)
-            | ASType::List(_) => true,
-            _ => false,
-        }
-    }
-
-    pub fn decompose(&self) -> ((ASType, &'static str), Option<(ASType, &'static str)>) {
-        let first = match self {
-            ASType::List(element_type) => (ASType::Ptr(element_type.clone()), "_ptr"),
-            t => (t.clone(), ""),
-        };
-        let second = match self {
-            ASType::List(_element_type) => Some((ASType::Usize, "_count")),
-            _ => None,
-        };
-        (first, second)
-    }
-
-    pub fn name(self, name: String) -> Self {
-        match self {
-            ASType::Record(_) => ASType::Record(Some(name)),
-            x => x,
+            ASType::Void | ASType::Alias(_) => None,
+            ASType::U8 | ASType::I8 | ASType::Bool | ASType::Char => Some(1),
+            ASType::U16 | ASType::I16 => Some(2),
+            ASType::U64 | ASType::I64 | ASType::F64 => Some(8),
+            // Everything else, including pointers and addresses, is 32-bit
+            _ => Some(4),
         }
     }
 }
@@ -99,8 +83,8 @@ impl From<witx::IntRepr> for ASType {
 impl From<&witx::BuiltinType> for ASType {
     fn from(witx: &witx::BuiltinType) -> Self {
         match witx {
-            // Assembly script has no C char type
-            witx::BuiltinType::U8 { lang_c_char: _ }  => ASType::U8,
+            witx::BuiltinType::U8 { lang_c_char: true } => ASType::Char,
+            witx::BuiltinType::U8 { lang_c_char: false } => ASType::U8,
             witx::BuiltinType::U16 => ASType::U16,
             witx::BuiltinType::U32 { lang_ptr_size: true } => ASType::Usize,
             witx::BuiltinType::U32 { lang_ptr_size: false } => ASType::U32,
@@ -111,14 +95,21 @@ impl From<&witx::BuiltinType> for ASType {
             witx::BuiltinType::S64 => ASType::I64,
             witx::BuiltinType::F32 => ASType::F32,
             witx::BuiltinType::F64 => ASType::F64,
-            witx::BuiltinType::Char => ASType::Char
+            // Unicode scalar values are 32-bit
+            witx::BuiltinType::Char => ASType::U32,
         }
     }
 }
 
 impl From<&witx::Variant> for ASType {
-    fn from(_witx: &witx::Variant) -> Self {
-        ASType::Variant(None)
+    fn from(witx: &witx::Variant) -> Self {
+        if witx.is_bool() {
+            ASType::Bool
+        } else if witx.is_enum() {
+            ASType::from(&witx.tag_repr)
+        } else {
+            ASType::Variant(None)
+        }
     }
 }
 
@@ -128,8 +119,8 @@ impl From<&witx::IntRepr> for ASType {
             witx::IntRepr::U8 => ASType::U8,
             witx::IntRepr::U16 => ASType::U16,
             witx::IntRepr::U32 => ASType::U32,
-            witx::IntRepr::U64 => ASType::U64
-        } 
+            witx::IntRepr::U64 => ASType::U64,
+        }
     }
 }
 
@@ -153,13 +144,9 @@ impl From<&witx::NamedType> for ASType {
 
 impl From<&witx::TypeRef> for ASType {
     fn from(witx: &witx::TypeRef) -> Self {
-        let type_name = witx.type_name();
-        match witx.type_().as_ref() {
-            witx::Type::Builtin(x) => ASType::from(x).name(type_name),
-            x @ witx::Type::List(_)
-            | x @ witx::Type::Pointer(_)
-            | x @ witx::Type::ConstPointer(_) => ASType::from(x).name(type_name),
-            _ => ASType::Alias(type_name),
+        match witx {
+            witx::TypeRef::Name(x) => x.as_ref().into(),
+            witx::TypeRef::Value(x) => x.as_ref().into(),
         }
     }
 }
@@ -173,7 +160,10 @@ impl From<&witx::Type> for ASType {
             witx::Type::Handle(x) => x.into(),
             witx::Type::List(x) => ASType::List(Box::new(x.into())),
             witx::Type::Variant(x) => x.into(),
-            witx::Type::Record(_) => ASType::Record(None),
+            witx::Type::Record(x) => match x.bitflags_repr() {
+                Some(repr) => repr.into(),
+                None => ASType::Record(None),
+            },
         }
     }
 }
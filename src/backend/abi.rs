@@ -0,0 +1,119 @@
+/// A parameter of the core WebAssembly signature of a function
+pub struct Param<'a> {
+    pub name: String,
+    pub kind: ParamKind<'a>,
+}
+
+pub enum ParamKind<'a> {
+    /// Value passed as-is, or by address for records and non-enum variants
+    Value(&'a witx::TypeRef),
+    /// Pointer to the first element of a list
+    ListPtr(&'a witx::TypeRef),
+    /// Number of elements in a list
+    ListLen,
+    /// Pointer to the location where the function stores a result
+    Out(&'a witx::TypeRef),
+}
+
+/// Core WebAssembly signature of a function, following the `Preview1` ABI
+pub struct Signature<'a> {
+    pub params: Vec<Param<'a>>,
+    /// Type of the value returned by the function, if any
+    pub result: Option<&'a witx::TypeRef>,
+    /// `result` is the error type of an `expected`, whose `ok` value is
+    /// returned through `Out` parameters
+    pub returns_error: bool,
+    /// Type of the `ok` value of an `expected` result
+    pub ok: Option<&'a witx::TypeRef>,
+}
+
+impl<'a> Signature<'a> {
+    pub fn new(func: &'a witx::InterfaceFunc) -> Self {
+        let mut params = vec![];
+        for param in &func.params {
+            let name = param.name.as_str();
+            match param.tref.type_().as_ref() {
+                witx::Type::List(element_type) => {
+                    params.push(Param {
+                        name: format!("{}_ptr", name),
+                        kind: ParamKind::ListPtr(element_type),
+                    });
+                    params.push(Param {
+                        name: format!("{}_len", name),
+                        kind: ParamKind::ListLen,
+                    });
+                }
+                _ => params.push(Param {
+                    name: name.to_string(),
+                    kind: ParamKind::Value(&param.tref),
+                }),
+            }
+        }
+        let mut signature = Signature {
+            params,
+            result: None,
+            returns_error: false,
+            ok: None,
+        };
+        let result = match func.results.first() {
+            None => return signature,
+            Some(result) => result,
+        };
+        let expected = match result.tref.type_().as_ref() {
+            witx::Type::Variant(variant) => variant.as_expected(),
+            _ => None,
+        };
+        let (ok, err) = match expected {
+            None => {
+                signature.result = Some(&result.tref);
+                return signature;
+            }
+            Some(expected) => expected,
+        };
+        signature.result = err;
+        signature.returns_error = err.is_some();
+        signature.ok = ok;
+        if let Some(ok) = ok {
+            match ok.type_().as_ref() {
+                witx::Type::Record(record) if record.is_tuple() => {
+                    for (i, member) in record.members.iter().enumerate() {
+                        signature.params.push(Param {
+                            name: format!("result{}_ptr", i),
+                            kind: ParamKind::Out(&member.tref),
+                        });
+                    }
+                }
+                _ => signature.params.push(Param {
+                    name: "result_ptr".to_string(),
+                    kind: ParamKind::Out(ok),
+                }),
+            }
+        }
+        signature
+    }
+}
+
+/// Records and variants with a payload are passed by address
+pub fn is_passed_by_reference(tref: &witx::TypeRef) -> bool {
+    match tref.type_().as_ref() {
+        witx::Type::Record(record) => record.bitflags_repr().is_none(),
+        witx::Type::Variant(variant) => !variant.is_enum(),
+        _ => false,
+    }
+}
+
+/// Lists of `char` are strings, stored as UTF-8 bytes
+pub fn is_string(element_type: &witx::TypeRef) -> bool {
+    matches!(
+        element_type.type_().as_ref(),
+        witx::Type::Builtin(witx::BuiltinType::Char)
+    )
+}
+
+/// Constants are written in hexadecimal for flags, and in decimal otherwise
+pub fn constant_value(type_: &witx::NamedType, value: u64) -> String {
+    match type_.type_().as_ref() {
+        witx::Type::Record(record) if record.bitflags_repr().is_some() => format!("{:#x}", value),
+        _ => value.to_string(),
+    }
+}
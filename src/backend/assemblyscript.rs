@@ -0,0 +1,1327 @@
+use witx::Layout;
+
+use super::abi::{self, ParamKind, Signature};
+use super::{Backend, Options};
+use crate::astype::*;
+use crate::error::*;
+use crate::pretty_writer::PrettyWriter;
+use std::io::Write;
+
+/// Types defined by the header, that files containing modules import
+const HEADER_TYPES: &[&str] = &[
+    "handle",
+    "char",
+    "ptr",
+    "mut_ptr",
+    "untyped_ptr",
+    "struct",
+    "union",
+    "wasi_string_ptr",
+    "WasiString",
+    "WasiArray",
+];
+
+const KEYWORDS: &[&str] = &[
+    "as", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+    "delete", "do", "else", "enum", "export", "extends", "false", "finally", "for", "function",
+    "if", "implements", "import", "in", "instanceof", "interface", "let", "new", "null",
+    "package", "private", "protected", "public", "return", "static", "super", "switch", "this",
+    "throw", "true", "try", "type", "typeof", "var", "void", "while", "with", "yield",
+];
+
+pub struct AssemblyScript {
+    wrappers: bool,
+}
+
+impl AssemblyScript {
+    pub fn new() -> Self {
+        Self::with_options(&Options::default())
+    }
+
+    pub fn with_options(options: &Options) -> Self {
+        AssemblyScript {
+            wrappers: options.wrappers,
+        }
+    }
+}
+
+impl Default for AssemblyScript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Backend<W> for AssemblyScript {
+    fn header(&mut self, w: &mut PrettyWriter<W>) -> Result<(), Error> {
+        Self::write_notice(w)?;
+        w.write_lines(
+            "
+export type handle = i32;
+export type char = u8;
+export type ptr<T> = usize;
+export type mut_ptr<T> = usize;
+export type untyped_ptr = usize;
+export type struct<T> = usize;
+export type union<T> = usize;
+export type wasi_string_ptr = ptr<char>;
+",
+        )?;
+        w.write_lines(
+            "
+@unmanaged
+export class WasiString {
+    ptr: wasi_string_ptr;
+    length: usize;
+
+    constructor(str: string) {
+        let wasiString = String.UTF8.encode(str, false);
+        // @ts-ignore: cast
+        this.ptr = changetype<wasi_string_ptr>(wasiString);
+        this.length = wasiString.byteLength;
+    }
+
+    toString(): string {
+        let tmp = new ArrayBuffer(this.length as u32);
+        memory.copy(changetype<usize>(tmp), this.ptr, this.length);
+        return String.UTF8.decode(tmp);
+    }
+}
+
+@unmanaged
+export class WasiArray<T> {
+    ptr: ptr<T>;
+    length: usize;
+
+    constructor(array: ArrayBufferView) {
+        // @ts-ignore: cast
+        this.ptr = array.dataStart;
+        this.length = array.byteLength;
+    }
+}
+",
+        )?
+        .eob()?;
+        if self.wrappers {
+            Self::define_result(w)?;
+        }
+        Ok(())
+    }
+
+    fn define_type_docs(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+    ) -> Result<(), Error> {
+        let docs = &type_.docs;
+        if docs.is_empty() {
+            w.write_line(format!("/** {} */", type_.name.as_str()))?;
+        } else {
+            Self::write_docs(w, docs)?;
+        }
+        Ok(())
+    }
+
+    fn define_alias(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        other_type: &witx::NamedType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "export type {} = {};",
+            ASType::from(type_),
+            ASType::from(other_type)
+        ))?;
+        Ok(())
+    }
+
+    fn define_handle(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        _handle: &witx::HandleDatatype,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "export type {} = {};",
+            ASType::from(type_),
+            ASType::Handle
+        ))?;
+        Ok(())
+    }
+
+    fn define_builtin(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        builtin: &witx::BuiltinType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "export type {} = {};",
+            ASType::from(type_),
+            ASType::from(builtin)
+        ))?;
+        Ok(())
+    }
+
+    fn define_record(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error> {
+        let as_type = ASType::from(type_);
+        if let Some(repr) = record.bitflags_repr() {
+            w.write_line(format!("export type {} = {};", as_type, ASType::from(repr)))?;
+            let values = record.members.iter().enumerate().map(|(i, member)| {
+                (member.name.as_str(), format!("{:#x}", 1u64 << i), &member.docs)
+            });
+            w.write_line(format!("export namespace {} {{", as_type))?;
+            {
+                let mut w = w.new_block();
+                Self::define_constants(&mut w, &as_type, values)?;
+                w.eob()?;
+                Self::define_flags_helpers(&mut w, &as_type, record)?;
+            }
+            w.write_line("}")?;
+            return Ok(());
+        }
+        let mut fields = vec![];
+        w.write_line("// @ts-ignore: decorator")?
+            .write_line("@unmanaged")?
+            .write_line(format!("export class {} {{", as_type))?;
+        {
+            let mut w = w.new_block();
+            let mut offset = 0;
+            let mut accessors = vec![];
+            for member_layout in record.member_layout() {
+                let member = member_layout.member;
+                Self::write_padding(&mut w, "pad", offset, member_layout.offset - offset)?;
+                offset = member_layout.offset;
+                let member_name = Self::name(member.name.as_str());
+                let member_size = member.tref.mem_size();
+                if Self::is_stored_inline(&member.tref) {
+                    // Classes can only be referenced, so the storage is reserved here,
+                    // and the member is accessed through its address
+                    Self::write_padding(&mut w, member.name.as_str(), offset, member_size)?;
+                    accessors.push((member, offset));
+                } else {
+                    Self::write_docs(&mut w, &member.docs)?;
+                    w.write_line(format!(
+                        "{}: {};",
+                        member_name,
+                        Self::field_type(&member.tref)
+                    ))?;
+                    fields.push((member_name, offset));
+                    // The rest of the member, if AssemblyScript stores it in fewer bytes
+                    let field_size = Self::field_size(&member.tref);
+                    Self::write_padding(
+                        &mut w,
+                        "pad",
+                        offset + field_size,
+                        member_size - field_size,
+                    )?;
+                }
+                offset += member_size;
+            }
+            Self::write_padding(&mut w, "pad", offset, record.mem_size() - offset)?;
+            for (member, offset) in accessors {
+                let member_type = Self::inline_type(&member.tref);
+                w.eob()?;
+                Self::write_docs(&mut w, &member.docs)?;
+                w.write_line(format!(
+                    "get {}(): {} {{",
+                    Self::name(member.name.as_str()),
+                    member_type
+                ))?;
+                w.new_block().write_line(format!(
+                    "return changetype<{}>(changetype<usize>(this) + {});",
+                    member_type, offset
+                ))?;
+                w.write_line("}")?;
+            }
+        }
+        w.write_line("}")?;
+        Self::define_layout_assertions(w, &as_type, record.mem_size(), &fields)?;
+        Ok(())
+    }
+
+    fn define_variant(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        union: &witx::Variant,
+    ) -> Result<(), Error> {
+        let as_type = ASType::from(type_);
+        let as_tag = ASType::from(&union.tag_repr);
+        let variants = &union.cases;
+        if union.is_bool() {
+            w.write_line(format!("export type {} = {};", as_type, ASType::Bool))?;
+            return Ok(());
+        }
+        if union.is_enum() {
+            w.write_line(format!("export type {} = {};", as_type, as_tag))?;
+            let values = variants
+                .iter()
+                .enumerate()
+                .map(|(i, case)| (case.name.as_str(), i.to_string(), &case.docs));
+            w.write_line(format!("export namespace {} {{", as_type))?;
+            {
+                let mut w = w.new_block();
+                Self::define_constants(&mut w, &as_type, values)?;
+                w.eob()?;
+                Self::define_enum_name(&mut w, &as_type, union)?;
+            }
+            w.write_line("}")?;
+            return Ok(());
+        }
+
+        let val_offset = union.payload_offset();
+        let val_size = union.mem_size() - val_offset;
+        let tag_size = union.tag_repr.to_builtin().mem_size();
+        w.write_line("// @ts-ignore: decorator")?
+            .write_line("@unmanaged")?
+            .write_line(format!("export class {} {{", as_type))?;
+        {
+            let mut w = w.new_block();
+            w.write_line(format!("tag: {};", as_tag))?;
+            Self::write_padding(&mut w, "pad", tag_size, union.mem_size() - tag_size)?;
+            w.eob()?;
+
+            w.write_line(format!("constructor(tag: {}) {{", as_tag))?;
+            {
+                let mut w = w.new_block();
+                w.write_line("this.tag = tag;")?.write_line(format!(
+                    "memory.fill(changetype<usize>(this) + {}, 0, {});",
+                    val_offset, val_size
+                ))?;
+            }
+            w.write_line("}")?.eob()?;
+
+            w.write_line("// @ts-ignore: default")?.write_line(format!(
+                "static new<T>(tag: {}, val: T = 0): {} {{",
+                as_tag, as_type
+            ))?;
+            {
+                let mut w = w.new_block();
+                w.write_line(format!("let tu = new {}(tag);", as_type))?
+                    .write_line("tu.set(val);")?
+                    .write_line("return tu;")?;
+            }
+            w.write_line("}")?.eob()?;
+
+            w.write_line("get<T>(): T {")?;
+            {
+                let mut w = w.new_block();
+                w.write_line("// @ts-ignore: cast")?
+                    .write_line(format!(
+                        "let valBuf = changetype<usize>(this) + {};",
+                        val_offset
+                    ))?
+                    .write_line("if (isReference<T>()) {")?;
+                w.new_block().write_line("return changetype<T>(valBuf);")?;
+                w.write_line("} else {")?;
+                w.new_block().write_line("return load<T>(valBuf);")?;
+                w.write_line("}")?;
+            }
+            w.write_line("}")?.eob()?;
+
+            w.write_line("// @ts-ignore: default")?
+                .write_line("set<T>(val: T = 0): void {")?;
+            {
+                let mut w = w.new_block();
+                w.write_line("// @ts-ignore: cast")?
+                    .write_line(format!(
+                        "let valBuf = changetype<usize>(this) + {};",
+                        val_offset
+                    ))?
+                    .write_line(format!("memory.fill(valBuf, 0, {});", val_size))?
+                    .write_line("if (isReference<T>()) {")?;
+                w.new_block().write_line(
+                    "(val !== null) && memory.copy(valBuf, changetype<usize>(val), offsetof<T>());",
+                )?;
+                w.write_line("} else {")?;
+                w.new_block().write_line("store<T>(valBuf, val)")?;
+                w.write_line("}")?;
+            }
+            w.write_line("}")?;
+
+            for (i, variant) in variants.iter().enumerate() {
+                w.eob()?;
+                Self::define_variant_case(&mut w, &as_type, union, i, variant)?;
+            }
+        }
+        w.write_line("}")?;
+        let fields = [("tag".to_string(), 0)];
+        Self::define_layout_assertions(w, &as_type, union.mem_size(), &fields)?;
+        Ok(())
+    }
+
+    fn define_list(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        element_type: &witx::TypeRef,
+    ) -> Result<(), Error> {
+        if abi::is_string(element_type) {
+            w.write_line(format!("export type {} = WasiString;", ASType::from(type_)))?;
+            return Ok(());
+        }
+        w.write_line(format!(
+            "export type {} = WasiArray<{}>;",
+            ASType::from(type_),
+            Self::stored_type(element_type)
+        ))?;
+        Ok(())
+    }
+
+    fn define_pointer(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        pointee_type: &witx::TypeRef,
+        is_const: bool,
+    ) -> Result<(), Error> {
+        let as_pointee_type = Box::new(ASType::from(pointee_type));
+        let as_pointer_type = if is_const {
+            ASType::Ptr(as_pointee_type)
+        } else {
+            ASType::MutPtr(as_pointee_type)
+        };
+        w.write_line(format!(
+            "export type {} = {};",
+            ASType::from(type_),
+            as_pointer_type
+        ))?;
+        Ok(())
+    }
+
+    fn define_constants(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        constants: &[&witx::Constant],
+    ) -> Result<(), Error> {
+        for constant in constants {
+            let name = format!("{}_{}", type_.name.as_str(), constant.name.as_str());
+            Self::write_docs(w, &constant.docs)?;
+            w.write_line(format!(
+                "export const {}: {} = {};",
+                Self::constant_name(&name),
+                ASType::from(type_),
+                abi::constant_value(type_, constant.value)
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_module(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module: &witx::Module,
+    ) -> Result<(), Error> {
+        w.eob()?.write_line(format!(
+            "// ----------------------[{}]----------------------",
+            module.name.as_str()
+        ))?;
+        Ok(())
+    }
+
+    fn define_func(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module_name: &str,
+        func: &witx::InterfaceFunc,
+    ) -> Result<(), Error> {
+        let docs = &func.docs;
+        let name = func.name.as_str();
+        if docs.is_empty() {
+            w.write_line(format!("\n/** {} */", name))?;
+        } else {
+            Self::write_docs(w, docs)?;
+        }
+        let s_in: Vec<_> = func
+            .params
+            .iter()
+            .map(|param| param.name.as_str().to_string())
+            .collect();
+        let s_out: Vec<_> = func
+            .results
+            .iter()
+            .map(|param| param.name.as_str().to_string())
+            .collect();
+        w.write_line("/**")?
+            .write_line(format!(" * in:  {}", s_in.join(", ")))?
+            .write_line(format!(" * out: {}", s_out.join(", ")))?
+            .write_line(" */")?;
+        w.write_line("// @ts-ignore: decorator")?
+            .write_line(format!("@external(\"{}\", \"{}\")", module_name, name))?
+            .write_line(format!("export declare function {}(", Self::name(name)))?;
+
+        let signature = Signature::new(func);
+        let (as_params, as_results): (Vec<_>, Vec<_>) = signature
+            .params
+            .iter()
+            .partition(|param| !matches!(param.kind, ParamKind::Out(_)));
+        let as_params: Vec<_> = as_params
+            .iter()
+            .map(|param| format!("{}: {}", Self::name(&param.name), Self::param_to_as(param)))
+            .collect();
+        let as_results: Vec<_> = as_results
+            .iter()
+            .map(|param| format!("{}: {}", Self::name(&param.name), Self::param_to_as(param)))
+            .collect();
+        if !as_params.is_empty() {
+            if !as_results.is_empty() {
+                w.continuation()?
+                    .write(as_params.join(", "))?
+                    .write(",")?
+                    .eol()?;
+            } else {
+                w.continuation()?.write_line(as_params.join(", "))?;
+            }
+        }
+        let return_as_type_and_comment = match (signature.result, func.results.first()) {
+            (Some(tref), Some(result)) => (
+                ASType::from(tref),
+                format!(" /* {} */", result.name.as_str()),
+            ),
+            _ => (ASType::Void, "".to_string()),
+        };
+        if !as_results.is_empty() {
+            w.continuation()?.write_line(as_results.join(", "))?;
+        }
+        w.write_line(format!(
+            "): {}{};",
+            return_as_type_and_comment.0, return_as_type_and_comment.1
+        ))?;
+        if self.wrappers {
+            Self::define_func_wrapper(w, func, &signature)?;
+        }
+        Ok(())
+    }
+
+    fn define_imports(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        types_file: &str,
+        type_names: &[String],
+    ) -> Result<(), Error> {
+        Self::write_notice(w)?;
+        let mut names: Vec<_> = HEADER_TYPES.iter().map(|x| x.to_string()).collect();
+        if self.wrappers {
+            names.push("Result".to_string());
+        }
+        names.extend(type_names.iter().cloned());
+        w.eob()?.write_line("import {")?;
+        {
+            let mut w = w.new_block();
+            for name in &names {
+                w.write_line(format!("{},", name))?;
+            }
+        }
+        w.write_line(format!("}} from \"./{}\";", types_file))?;
+        Ok(())
+    }
+
+    fn define_index(&mut self, w: &mut PrettyWriter<W>, files: &[String]) -> Result<(), Error> {
+        Self::write_notice(w)?;
+        w.eob()?;
+        for file in files {
+            w.write_line(format!("export * from \"./{}\";", file))?;
+        }
+        Ok(())
+    }
+}
+
+impl AssemblyScript {
+    /// Warn that the file is generated
+    fn write_notice<T: Write>(w: &mut PrettyWriter<T>) -> Result<(), Error> {
+        w.write_lines(
+            "
+/*
+ * This file was automatically generated by as-witx - Do not edit manually.
+ */",
+        )?;
+        Ok(())
+    }
+
+    /// Define constants of type `as_type`, named after witx cases or flags
+    fn define_constants<'a, T: Write>(
+        w: &mut PrettyWriter<T>,
+        as_type: &ASType,
+        values: impl Iterator<Item = (&'a str, String, &'a String)>,
+    ) -> Result<(), Error> {
+        for (name, value, docs) in values {
+            Self::write_docs(w, docs)?;
+            w.write_line("// @ts-ignore: decorator")?
+                .write_line("@inline")?
+                .write_line(format!(
+                    "export const {}: {} = {};",
+                    Self::constant_name(name),
+                    as_type,
+                    value
+                ))?;
+        }
+        Ok(())
+    }
+
+    /// Define functions to test and update the flags of a value of type `as_type`
+    fn define_flags_helpers<T: Write>(
+        w: &mut PrettyWriter<T>,
+        as_type: &ASType,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error> {
+        let helpers = [
+            ("has", ASType::Bool, "(flags & flag) == flag"),
+            ("set", as_type.clone(), "flags | flag"),
+            ("clear", as_type.clone(), "flags & ~flag"),
+        ];
+        for (name, return_type, expr) in helpers.iter() {
+            w.write_line("// @ts-ignore: decorator")?
+                .write_line("@inline")?
+                .write_line(format!(
+                    "export function {}(flags: {}, flag: {}): {} {{",
+                    name, as_type, as_type, return_type
+                ))?;
+            w.new_block().write_line(format!("return {};", expr))?;
+            w.write_line("}")?.eob()?;
+        }
+        w.write_line("/** Names of the flags that are set, separated with `|` */")?
+            .write_line(format!("export function toString(flags: {}): string {{", as_type))?;
+        {
+            let mut w = w.new_block();
+            w.write_line("let names = new Array<string>();")?;
+            for member in &record.members {
+                w.write_line(format!(
+                    "if ((flags & {}) != 0) names.push(\"{}\");",
+                    Self::constant_name(member.name.as_str()),
+                    member.name.as_str()
+                ))?;
+            }
+            w.write_line("return names.join(\"|\");")?;
+        }
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    /// Define a function returning the witx name of a case of an enum
+    fn define_enum_name<T: Write>(
+        w: &mut PrettyWriter<T>,
+        as_type: &ASType,
+        union: &witx::Variant,
+    ) -> Result<(), Error> {
+        w.write_line("/** Name of a case, as defined in witx */")?
+            .write_line(format!("export function name(value: {}): string {{", as_type))?;
+        {
+            let mut w = w.new_block();
+            w.write_line("switch (value) {")?;
+            {
+                let mut w = w.new_block();
+                for case in &union.cases {
+                    w.write_line(format!(
+                        "case {}: return \"{}\";",
+                        Self::constant_name(case.name.as_str()),
+                        case.name.as_str()
+                    ))?;
+                }
+                w.write_line("default: return \"unknown\";")?;
+            }
+            w.write_line("}")?;
+        }
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    fn constant_name(name: &str) -> String {
+        Self::name(&name.to_uppercase())
+    }
+
+    fn define_variant_case_accessors<T: Write>(
+        w: &mut PrettyWriter<T>,
+        as_type: &ASType,
+        union: &witx::Variant,
+        i: usize,
+        variant: &witx::Case,
+    ) -> Result<(), Error> {
+        let variant_name = variant.name.as_str();
+        match variant.tref.as_ref() {
+            None => {
+                w.write_line(format!("static {}(): {} {{", Self::name(variant_name), as_type))?
+                    .indent()?
+                    .write_line(format!("return {}.new({});", as_type, i))?
+                    .write_line("}")?
+                    .eob()?;
+
+                w.write_line(format!("set_{}(): void {{", variant_name))?
+                    .indent()?
+                    .write_line(format!("this.tag = {};", i))?
+                    .write_line("}")?
+                    .eob()?;
+
+                w.write_line(format!("is_{}(): bool {{", variant_name))?
+                    .indent()?
+                    .write_line(format!("return this.tag === {};", i))?
+                    .write_line("}")?;
+            }
+            Some(variant_type)
+                if Self::is_stored_inline(variant_type)
+                    && Self::inline_type(variant_type) == ASType::Usize =>
+            {
+                Self::define_variant_case_address_accessors(
+                    w,
+                    as_type,
+                    union,
+                    i,
+                    variant_name,
+                    variant_type,
+                )?;
+            }
+            Some(variant_type) => {
+                let as_variant_type = Self::stored_type(variant_type);
+                // Payloads stored inline are returned as a class, that can be `null`
+                let is_nullable = Self::is_stored_inline(variant_type);
+                w.write_line(format!(
+                    "static {}(val: {}): {} {{",
+                    Self::name(variant_name),
+                    as_variant_type,
+                    as_type
+                ))?;
+                w.new_block()
+                    .write_line(format!("return {}.new({}, val);", as_type, i))?;
+                w.write_line("}")?.eob()?;
+
+                w.write_line(format!(
+                    "set_{}(val: {}): void {{",
+                    variant_name, as_variant_type
+                ))?;
+                {
+                    w.new_block()
+                        .write_line(format!("this.tag = {};", i))?
+                        .write_line("this.set(val);")?;
+                }
+                w.write_line("}")?.eob()?;
+
+                w.write_line(format!("is_{}(): bool {{", variant_name))?
+                    .indent()?
+                    .write_line(format!("return this.tag === {};", i))?
+                    .write_line("}")?
+                    .eob()?;
+
+                if is_nullable {
+                    w.write_line(format!(
+                        "get_{}(): {} | null {{",
+                        variant_name, as_variant_type
+                    ))?;
+                } else {
+                    w.write_line(format!("get_{}(): {} {{", variant_name, as_variant_type))?;
+                }
+                {
+                    let mut w = w.new_block();
+                    if is_nullable {
+                        w.write_line(format!("if (this.tag !== {}) {{ return null; }}", i))?;
+                    }
+                    w.write_line(format!("return this.get<{}>();", as_variant_type))?;
+                }
+                w.write_line("}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Anonymous payloads have no class, so they are accessed through their
+    /// address, and copied from an address
+    fn define_variant_case_address_accessors<T: Write>(
+        w: &mut PrettyWriter<T>,
+        as_type: &ASType,
+        union: &witx::Variant,
+        i: usize,
+        variant_name: &str,
+        variant_type: &witx::TypeRef,
+    ) -> Result<(), Error> {
+        let val_offset = union.payload_offset();
+        let val_size = union.mem_size() - val_offset;
+        w.write_line(format!(
+            "static {}(val: usize): {} {{",
+            Self::name(variant_name),
+            as_type
+        ))?;
+        w.new_block()
+            .write_line(format!("let tu = new {}({});", as_type, i))?
+            .write_line(format!(
+                "memory.copy(changetype<usize>(tu) + {}, val, {});",
+                val_offset,
+                variant_type.mem_size()
+            ))?
+            .write_line("return tu;")?;
+        w.write_line("}")?.eob()?;
+
+        w.write_line(format!("set_{}(val: usize): void {{", variant_name))?;
+        {
+            w.new_block()
+                .write_line(format!("this.tag = {};", i))?
+                .write_line(format!(
+                    "let valBuf = changetype<usize>(this) + {};",
+                    val_offset
+                ))?
+                .write_line(format!("memory.fill(valBuf, 0, {});", val_size))?
+                .write_line(format!(
+                    "memory.copy(valBuf, val, {});",
+                    variant_type.mem_size()
+                ))?;
+        }
+        w.write_line("}")?.eob()?;
+
+        w.write_line(format!("is_{}(): bool {{", variant_name))?
+            .indent()?
+            .write_line(format!("return this.tag === {};", i))?
+            .write_line("}")?
+            .eob()?;
+
+        w.write_line(format!("get_{}(): usize {{", variant_name))?;
+        w.new_block()
+            .write_line(format!("return changetype<usize>(this) + {};", val_offset))?;
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    fn define_variant_case<T: Write>(
+        w: &mut PrettyWriter<T>,
+        as_type: &ASType,
+        union: &witx::Variant,
+        i: usize,
+        variant: &witx::Case,
+    ) -> Result<(), Error> {
+        let variant_name = variant.name.as_str();
+        match variant.tref.as_ref() {
+            None => {
+                w.write_line(format!("// --- {}: void if tag={}", variant_name, i))?;
+            }
+            Some(variant_type) => {
+                w.write_line(format!(
+                    "// --- {}: {} if tag={}",
+                    variant_name,
+                    Self::stored_type(variant_type),
+                    i
+                ))?;
+            }
+        }
+        w.eob()?;
+        Self::define_variant_case_accessors(w, as_type, union, i, variant)?;
+        Ok(())
+    }
+
+    fn write_docs<T: Write>(w: &mut PrettyWriter<T>, docs: &str) -> Result<(), Error> {
+        if docs.is_empty() {
+            return Ok(());
+        }
+        w.write_line("/**")?;
+        for docs_line in docs.lines() {
+            w.write_line(format!(" * {}", docs_line))?;
+        }
+        w.write_line(" */")?;
+        Ok(())
+    }
+
+    /// Define a function taking and returning AssemblyScript values, on top of a raw import
+    fn define_func_wrapper<T: Write>(
+        w: &mut PrettyWriter<T>,
+        func: &witx::InterfaceFunc,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        let name = func.name.as_str();
+        let mut params = vec![];
+        let mut param_names = vec![];
+        let mut args = vec![];
+        let mut prologue = vec![];
+        for param in &func.params {
+            let param_name = Self::name(param.name.as_str());
+            param_names.push(param_name.clone());
+            match param.tref.type_().as_ref() {
+                witx::Type::List(element_type) if abi::is_string(element_type) => {
+                    let buf_name = format!("{}_buf", param.name.as_str());
+                    params.push(format!("{}: string", param_name));
+                    prologue.push(format!(
+                        "let {} = String.UTF8.encode({});",
+                        buf_name, param_name
+                    ));
+                    args.push(format!("changetype<usize>({})", buf_name));
+                    args.push(format!("<usize>{}.byteLength", buf_name));
+                }
+                witx::Type::List(element_type) if Self::is_byte(element_type) => {
+                    params.push(format!("{}: ArrayBuffer", param_name));
+                    args.push(format!("changetype<usize>({})", param_name));
+                    args.push(format!("<usize>{}.byteLength", param_name));
+                }
+                witx::Type::List(element_type) => {
+                    params.push(format!("{}: ArrayBufferView", param_name));
+                    args.push(format!("{}.dataStart", param_name));
+                    args.push(format!(
+                        "<usize>({}.byteLength / {})",
+                        param_name,
+                        element_type.mem_size()
+                    ));
+                }
+                _ => {
+                    params.push(format!("{}: {}", param_name, ASType::from(&param.tref)));
+                    args.push(param_name);
+                }
+            }
+        }
+
+        // Lines decoding the `ok` value, and the expression it is then available as
+        let mut epilogue = vec![];
+        let (ok_type, ok_value) = match signature.ok {
+            None => (ASType::Void, "".to_string()),
+            Some(ok) => {
+                prologue.push(format!("let result_ptr = heap.alloc({});", ok.mem_size()));
+                match ok.type_().as_ref() {
+                    witx::Type::Record(record) if record.is_tuple() => {
+                        for member_layout in record.member_layout() {
+                            args.push(format!("result_ptr + {}", member_layout.offset));
+                        }
+                    }
+                    _ => args.push("result_ptr".to_string()),
+                }
+                match ok.type_().as_ref() {
+                    witx::Type::List(element_type) if abi::is_string(element_type) => {
+                        epilogue.push(
+                            "let result = changetype<WasiString>(result_ptr).toString();"
+                                .to_string(),
+                        );
+                        epilogue.push("heap.free(result_ptr);".to_string());
+                        (ASType::Alias("string".to_string()), "result".to_string())
+                    }
+                    witx::Type::Record(record)
+                        if record.is_tuple() && matches!(ok, witx::TypeRef::Value(_)) =>
+                    {
+                        let ok_type = Self::define_func_results(w, name, record)?;
+                        let mut values = vec![];
+                        for member_layout in record.member_layout() {
+                            values.push(Self::decode_result_member(
+                                &mut epilogue,
+                                &member_layout.member.tref,
+                                member_layout.offset,
+                            ));
+                        }
+                        epilogue.push(format!(
+                            "let result = new {}({});",
+                            ok_type,
+                            values.join(", ")
+                        ));
+                        epilogue.push("heap.free(result_ptr);".to_string());
+                        (ok_type, "result".to_string())
+                    }
+                    _ if Self::is_stored_inline(ok) => {
+                        // The caller owns the value, and releases it with `heap.free()`
+                        let ok_type = Self::inline_type(ok);
+                        let ok_value = format!("changetype<{}>(result_ptr)", ok_type);
+                        (ok_type, ok_value)
+                    }
+                    _ => {
+                        let ok_type = ASType::from(ok);
+                        epilogue.push(format!("let result = load<{}>(result_ptr);", ok_type));
+                        epilogue.push("heap.free(result_ptr);".to_string());
+                        (ok_type, "result".to_string())
+                    }
+                }
+            }
+        };
+        let call = format!("{}({})", Self::name(name), args.join(", "));
+
+        let error_type = match signature.result {
+            Some(error_type) if signature.returns_error => ASType::from(error_type),
+            _ => {
+                let return_type = match signature.result {
+                    _ if func.noreturn => ASType::Void,
+                    Some(result) if signature.ok.is_none() => ASType::from(result),
+                    _ => ok_type,
+                };
+                w.eob()?;
+                Self::write_docs(w, &func.docs)?;
+                w.write_line(format!(
+                    "export function {}({}): {} {{",
+                    Self::wrapper_name(name),
+                    params.join(", "),
+                    return_type
+                ))?;
+                {
+                    let mut w = w.new_block();
+                    for line in &prologue {
+                        w.write_line(line)?;
+                    }
+                    if signature.ok.is_some() {
+                        w.write_line(format!("{};", call))?;
+                        for line in &epilogue {
+                            w.write_line(line)?;
+                        }
+                        w.write_line(format!("return {};", ok_value))?;
+                    } else if return_type != ASType::Void {
+                        w.write_line(format!("return {};", call))?;
+                    } else {
+                        w.write_line(format!("{};", call))?;
+                    }
+                }
+                w.write_line("}")?;
+                return Ok(());
+            }
+        };
+
+        // Functions without an `ok` value report success as `true`
+        let (ok_type, ok_value) = match ok_type {
+            ASType::Void => (ASType::Bool, "true".to_string()),
+            _ => (ok_type, ok_value),
+        };
+        let result_type = format!("Result<{}, {}>", ok_type, error_type);
+        let result_type_args = format!("<{}, {}>", ok_type, error_type);
+        let try_name = Self::wrapper_name(&format!("try_{}", name));
+        w.eob()?;
+        Self::write_docs(w, &func.docs)?;
+        w.write_line(format!(
+            "export function {}({}): {} {{",
+            try_name,
+            params.join(", "),
+            result_type
+        ))?;
+        {
+            let mut w = w.new_block();
+            for line in &prologue {
+                w.write_line(line)?;
+            }
+            w.write_line(format!("let error = {};", call))?
+                .write_line("if (error != 0) {")?;
+            {
+                let mut w = w.new_block();
+                if signature.ok.is_some() {
+                    w.write_line("heap.free(result_ptr);")?;
+                }
+                w.write_line(format!("return Result.err{}(error);", result_type_args))?;
+            }
+            w.write_line("}")?;
+            for line in &epilogue {
+                w.write_line(line)?;
+            }
+            w.write_line(format!(
+                "return Result.ok{}({});",
+                result_type_args, ok_value
+            ))?;
+        }
+        w.write_line("}")?;
+
+        w.eob()?;
+        Self::write_docs(w, &func.docs)?;
+        let expect = format!(
+            "{}({}).expect(\"{}\")",
+            try_name,
+            param_names.join(", "),
+            name
+        );
+        if signature.ok.is_some() {
+            w.write_line(format!(
+                "export function {}({}): {} {{",
+                Self::wrapper_name(name),
+                params.join(", "),
+                ok_type
+            ))?;
+            w.new_block().write_line(format!("return {};", expect))?;
+        } else {
+            w.write_line(format!(
+                "export function {}({}): void {{",
+                Self::wrapper_name(name),
+                params.join(", ")
+            ))?;
+            w.new_block().write_line(format!("{};", expect))?;
+        }
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    /// Define a class holding the members of an anonymous tuple returned by `func_name`
+    fn define_func_results<T: Write>(
+        w: &mut PrettyWriter<T>,
+        func_name: &str,
+        record: &witx::RecordDatatype,
+    ) -> Result<ASType, Error> {
+        let results_type = ASType::Alias(Self::name(&format!("{}_results", func_name)));
+        let members: Vec<_> = record
+            .members
+            .iter()
+            .map(|member| {
+                (
+                    Self::name(member.name.as_str()),
+                    Self::result_member_type(&member.tref),
+                )
+            })
+            .collect();
+        w.eob()?
+            .write_line(format!("/** Values returned by `{}` */", func_name))?
+            .write_line(format!("export class {} {{", results_type))?;
+        {
+            let mut w = w.new_block();
+            for (name, type_) in &members {
+                w.write_line(format!("{}: {};", name, type_))?;
+            }
+            let params: Vec<_> = members
+                .iter()
+                .map(|(name, type_)| format!("{}: {}", name, type_))
+                .collect();
+            w.eob()?
+                .write_line(format!("constructor({}) {{", params.join(", ")))?;
+            {
+                let mut w = w.new_block();
+                for (name, _) in &members {
+                    w.write_line(format!("this.{} = {};", name, name))?;
+                }
+            }
+            w.write_line("}")?;
+        }
+        w.write_line("}")?;
+        Ok(results_type)
+    }
+
+    /// The type of a member of a tuple returned by a wrapper
+    fn result_member_type(tref: &witx::TypeRef) -> ASType {
+        match tref.type_().as_ref() {
+            witx::Type::List(element_type) if abi::is_string(element_type) => {
+                ASType::Alias("string".to_string())
+            }
+            _ if Self::is_stored_inline(tref) => Self::inline_type(tref),
+            _ => Self::field_type(tref),
+        }
+    }
+
+    /// An expression reading a member of a tuple stored at `result_ptr + offset`.
+    ///
+    /// Members stored inline, other than strings, are copied to a buffer the
+    /// caller owns, before `result_ptr` is released.
+    fn decode_result_member(
+        epilogue: &mut Vec<String>,
+        tref: &witx::TypeRef,
+        offset: usize,
+    ) -> String {
+        let member_type = Self::result_member_type(tref);
+        match tref.type_().as_ref() {
+            witx::Type::List(element_type) if abi::is_string(element_type) => {
+                format!("changetype<WasiString>(result_ptr + {}).toString()", offset)
+            }
+            _ if Self::is_stored_inline(tref) => {
+                let buf_name = format!("result{}", offset);
+                let size = tref.mem_size();
+                epilogue.push(format!("let {} = heap.alloc({});", buf_name, size));
+                epilogue.push(format!(
+                    "memory.copy({}, result_ptr + {}, {});",
+                    buf_name, offset, size
+                ));
+                format!("changetype<{}>({})", member_type, buf_name)
+            }
+            _ => format!("load<{}>(result_ptr + {})", member_type, offset),
+        }
+    }
+
+    /// Define the `Result` class returned by wrappers of functions that can fail
+    fn define_result<T: Write>(w: &mut PrettyWriter<T>) -> Result<(), Error> {
+        w.write_lines(
+            "
+/**
+ * Either the value returned by a function, or the error it failed with.
+ * Functions without a return value hold `true` when they succeed.
+ */
+export class Result<T, E> {
+    private _value!: T;
+    private _error!: E;
+    private _isOk: bool;
+
+    private constructor(isOk: bool) {
+        this._isOk = isOk;
+    }
+
+    static ok<T, E>(value: T): Result<T, E> {
+        let result = new Result<T, E>(true);
+        result._value = value;
+        return result;
+    }
+
+    static err<T, E>(error: E): Result<T, E> {
+        let result = new Result<T, E>(false);
+        result._error = error;
+        return result;
+    }
+
+    isOk(): bool {
+        return this._isOk;
+    }
+
+    isErr(): bool {
+        return !this._isOk;
+    }
+
+    get error(): E {
+        assert(!this._isOk, \"Result: not an error\");
+        return this._error;
+    }
+
+    /** The value, or throw an error prefixed with `message` */
+    expect(message: string): T {
+        if (!this._isOk) {
+            throw new Error(message + \": error \" + this._error.toString());
+        }
+        return this._value;
+    }
+
+    unwrap(): T {
+        return this.expect(\"Result\");
+    }
+
+    unwrapOr(value: T): T {
+        return this._isOk ? this._value : value;
+    }
+}
+",
+        )?
+        .eob()?;
+        Ok(())
+    }
+
+    /// Lists of bytes are passed as an `ArrayBuffer`
+    fn is_byte(element_type: &witx::TypeRef) -> bool {
+        matches!(
+            element_type.type_().as_ref(),
+            witx::Type::Builtin(witx::BuiltinType::U8 { .. })
+        )
+    }
+
+    /// Convert a witx function name to `camelCase`
+    fn wrapper_name(name: &str) -> String {
+        let mut camel_name = String::with_capacity(name.len());
+        let mut upper = false;
+        for c in name.chars() {
+            if c == '_' {
+                upper = true;
+            } else if upper {
+                camel_name.extend(c.to_uppercase());
+                upper = false;
+            } else {
+                camel_name.push(c);
+            }
+        }
+        if camel_name == name {
+            camel_name.push_str("Wrapper");
+        }
+        Self::name(&camel_name)
+    }
+
+    fn param_to_as(param: &abi::Param) -> ASType {
+        match &param.kind {
+            ParamKind::Value(tref) => ASType::from(*tref),
+            ParamKind::ListPtr(element_type) if abi::is_string(element_type) => {
+                ASType::Ptr(Box::new(ASType::Char))
+            }
+            ParamKind::ListPtr(element_type) => ASType::Ptr(Box::new(ASType::from(*element_type))),
+            ParamKind::ListLen => ASType::Usize,
+            ParamKind::Out(tref) => ASType::MutPtr(Box::new(ASType::from(*tref))),
+        }
+    }
+
+    /// Check at compile time that a class has the size and field offsets computed by witx
+    fn define_layout_assertions<T: Write>(
+        w: &mut PrettyWriter<T>,
+        as_type: &ASType,
+        size: usize,
+        fields: &[(String, usize)],
+    ) -> Result<(), Error> {
+        w.eob()?.write_line(format!(
+            "if (offsetof<{}>() != {}) ERROR(\"{}: size\");",
+            as_type, size, as_type
+        ))?;
+        for (name, offset) in fields {
+            w.write_line(format!(
+                "if (offsetof<{}>(\"{}\") != {}) ERROR(\"{}.{}: offset\");",
+                as_type, name, offset, as_type, name
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Reserve `len` bytes starting at `offset`, using naturally aligned fields
+    fn write_padding<T: Write>(
+        w: &mut PrettyWriter<T>,
+        name: &str,
+        mut offset: usize,
+        len: usize,
+    ) -> Result<(), Error> {
+        let end = offset + len;
+        while offset < end {
+            let size = [8, 4, 2, 1]
+                .iter()
+                .copied()
+                .find(|&size| offset.is_multiple_of(size) && offset + size <= end)
+                .unwrap();
+            w.write_line(format!("private __{}_{}: u{};", name, offset, size * 8))?;
+            offset += size;
+        }
+        Ok(())
+    }
+
+    /// Whether a value of that type is stored inline as a class rather than as a number
+    fn is_stored_inline(tref: &witx::TypeRef) -> bool {
+        match tref.type_().as_ref() {
+            witx::Type::List(_) => true,
+            _ => abi::is_passed_by_reference(tref),
+        }
+    }
+
+    /// The type of a record field that isn't stored inline.
+    ///
+    /// witx booleans are 32-bit, so they are stored as `u32` rather than as a 1-byte `bool`.
+    fn field_type(tref: &witx::TypeRef) -> ASType {
+        match tref.type_().as_ref() {
+            witx::Type::Variant(variant) if variant.is_bool() => ASType::U32,
+            _ => ASType::from(tref),
+        }
+    }
+
+    /// Size of the type a record field that isn't stored inline is declared with
+    fn field_size(tref: &witx::TypeRef) -> usize {
+        match (Self::field_type(tref).mem_size(), tref) {
+            (Some(size), _) => size,
+            (None, witx::TypeRef::Name(named_type)) => Self::field_size(&named_type.tref),
+            (None, witx::TypeRef::Value(_)) => tref.mem_size(),
+        }
+    }
+
+    /// The type of a value stored in a variant payload or in a list
+    fn stored_type(tref: &witx::TypeRef) -> ASType {
+        if Self::is_stored_inline(tref) {
+            Self::inline_type(tref)
+        } else {
+            Self::field_type(tref)
+        }
+    }
+
+    /// The class used to access a value stored inline
+    fn inline_type(tref: &witx::TypeRef) -> ASType {
+        match tref {
+            witx::TypeRef::Name(named_type) => ASType::from(named_type.as_ref()),
+            witx::TypeRef::Value(type_) => match type_.as_ref() {
+                witx::Type::List(element_type) if abi::is_string(element_type) => {
+                    ASType::Alias("WasiString".to_string())
+                }
+                witx::Type::List(element_type) => ASType::Alias(format!(
+                    "WasiArray<{}>",
+                    Self::stored_type(element_type)
+                )),
+                _ => ASType::Usize,
+            },
+        }
+    }
+
+    /// Escape an identifier that is a keyword or doesn't start with a letter
+    fn name(name: &str) -> String {
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            || KEYWORDS.contains(&name)
+        {
+            format!("_{}", name)
+        } else {
+            name.to_string()
+        }
+    }
+}
@@ -0,0 +1,506 @@
+use witx::Layout;
+
+use super::abi::{self, ParamKind, Signature};
+use super::Backend;
+use crate::error::*;
+use crate::pretty_writer::PrettyWriter;
+use std::io::Write;
+
+const KEYWORDS: &[&str] = &[
+    "auto", "bool", "break", "case", "char", "const", "continue", "default", "do", "double",
+    "else", "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while",
+];
+
+/// Prefix of every type name, to avoid clashes with standard types
+const PREFIX: &str = "witx_";
+
+pub struct C;
+
+impl C {
+    pub fn new() -> Self {
+        C
+    }
+}
+
+impl Default for C {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Backend<W> for C {
+    fn header(&mut self, w: &mut PrettyWriter<W>) -> Result<(), Error> {
+        w.write_lines(
+            "
+/*
+ * This file was automatically generated by as-witx - Do not edit manually.
+ */
+
+#pragma once
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+",
+        )?
+        .eob()?;
+        Ok(())
+    }
+
+    fn footer(&mut self, w: &mut PrettyWriter<W>) -> Result<(), Error> {
+        w.write_lines(
+            "#ifdef __cplusplus
+}
+#endif",
+        )?;
+        Ok(())
+    }
+
+    fn define_type_docs(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+    ) -> Result<(), Error> {
+        Self::write_docs(w, &type_.docs)
+    }
+
+    fn define_alias(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        other_type: &witx::NamedType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "typedef {} {};",
+            Self::type_name(other_type.name.as_str()),
+            Self::type_name(type_.name.as_str())
+        ))?;
+        Ok(())
+    }
+
+    fn define_handle(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        _handle: &witx::HandleDatatype,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "typedef int32_t {};",
+            Self::type_name(type_.name.as_str())
+        ))?;
+        Ok(())
+    }
+
+    fn define_builtin(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        builtin: &witx::BuiltinType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "typedef {} {};",
+            Self::builtin(builtin),
+            Self::type_name(type_.name.as_str())
+        ))?;
+        Ok(())
+    }
+
+    fn define_record(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        if let Some(repr) = record.bitflags_repr() {
+            w.write_line(format!(
+                "typedef {} {};",
+                Self::builtin(&repr.to_builtin()),
+                name
+            ))?;
+            for (i, member) in record.members.iter().enumerate() {
+                Self::write_docs(w, &member.docs)?;
+                w.write_line(format!(
+                    "#define {} (({})(1ULL << {}))",
+                    Self::constant_name(type_.name.as_str(), member.name.as_str()),
+                    name,
+                    i
+                ))?;
+            }
+            return Ok(());
+        }
+        w.write_line(format!("typedef struct {} {{", name))?;
+        {
+            let mut w = w.new_block();
+            for member in &record.members {
+                Self::write_docs(&mut w, &member.docs)?;
+                w.write_line(format!(
+                    "{};",
+                    Self::declaration(&member.tref, &Self::name(member.name.as_str()))
+                ))?;
+            }
+        }
+        w.write_line(format!("}} {};", name))?.eob()?;
+        Self::define_layout_assertions(w, type_)?;
+        for member_layout in record.member_layout() {
+            w.write_line(format!(
+                "_Static_assert(offsetof({}, {}) == {}, \"{}.{}: offset\");",
+                name,
+                Self::name(member_layout.member.name.as_str()),
+                member_layout.offset,
+                type_.name.as_str(),
+                member_layout.member.name.as_str()
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_variant(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        variant: &witx::Variant,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        if variant.is_bool() {
+            // witx booleans are 32-bit, and C's `bool` is usually a single byte
+            w.write_line(format!("typedef uint32_t {};", name))?;
+            return Ok(());
+        }
+        let tag_type = Self::builtin(&variant.tag_repr.to_builtin());
+        if variant.is_enum() {
+            w.write_line(format!("typedef {} {};", tag_type, name))?;
+            Self::define_tags(w, type_, variant, &name)?;
+            return Ok(());
+        }
+        w.write_line(format!("typedef struct {} {{", name))?;
+        {
+            let mut w = w.new_block();
+            w.write_line(format!("{} tag;", tag_type))?
+                .write_line("union {")?;
+            {
+                let mut w = w.new_block();
+                for case in &variant.cases {
+                    if let Some(case_type) = &case.tref {
+                        Self::write_docs(&mut w, &case.docs)?;
+                        w.write_line(format!(
+                            "{};",
+                            Self::declaration(case_type, &Self::name(case.name.as_str()))
+                        ))?;
+                    }
+                }
+            }
+            w.write_line("} u;")?;
+        }
+        w.write_line(format!("}} {};", name))?.eob()?;
+        Self::define_tags(w, type_, variant, tag_type)?;
+        w.eob()?;
+        Self::define_layout_assertions(w, type_)?;
+        w.write_line(format!(
+            "_Static_assert(offsetof({}, u) == {}, \"{}: payload offset\");",
+            name,
+            variant.payload_offset(),
+            type_.name.as_str()
+        ))?;
+        Ok(())
+    }
+
+    fn define_list(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        element_type: &witx::TypeRef,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        w.write_line(format!("typedef struct {} {{", name))?;
+        w.new_block()
+            .write_line(format!(
+                "{};",
+                Self::declaration_in_list(element_type, "ptr")
+            ))?
+            .write_line("size_t len;")?;
+        w.write_line(format!("}} {};", name))?.eob()?;
+        Self::define_layout_assertions(w, type_)?;
+        Ok(())
+    }
+
+    fn define_pointer(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        _pointee_type: &witx::TypeRef,
+        _is_const: bool,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "typedef {};",
+            Self::declaration(&type_.tref, &Self::type_name(type_.name.as_str()))
+        ))?;
+        Ok(())
+    }
+
+    fn define_constants(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        constants: &[&witx::Constant],
+    ) -> Result<(), Error> {
+        for constant in constants {
+            Self::write_docs(w, &constant.docs)?;
+            w.write_line(format!(
+                "#define {} (({}){})",
+                Self::constant_name(type_.name.as_str(), constant.name.as_str()),
+                Self::type_name(type_.name.as_str()),
+                abi::constant_value(type_, constant.value)
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_module(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module: &witx::Module,
+    ) -> Result<(), Error> {
+        w.eob()?
+            .write_line(format!(
+                "/* ----------------------[{}]---------------------- */",
+                module.name.as_str()
+            ))?
+            .eob()?;
+        Ok(())
+    }
+
+    fn define_func(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module_name: &str,
+        func: &witx::InterfaceFunc,
+    ) -> Result<(), Error> {
+        let name = func.name.as_str();
+        let signature = Signature::new(func);
+        let mut params: Vec<_> = signature
+            .params
+            .iter()
+            .map(|param| {
+                let param_name = Self::name(&param.name);
+                match &param.kind {
+                    ParamKind::Value(tref) if abi::is_passed_by_reference(tref) => {
+                        format!("const {} *{}", Self::type_ref(tref), param_name)
+                    }
+                    ParamKind::Value(tref) => Self::declaration(tref, &param_name),
+                    ParamKind::ListPtr(element_type) => {
+                        Self::declaration_in_list(element_type, &param_name)
+                    }
+                    ParamKind::ListLen => format!("size_t {}", param_name),
+                    ParamKind::Out(tref) => Self::declaration(tref, &format!("*{}", param_name)),
+                }
+            })
+            .collect();
+        if params.is_empty() {
+            params.push("void".to_string());
+        }
+        let return_type = match signature.result {
+            _ if func.noreturn => "_Noreturn void".to_string(),
+            None => "void".to_string(),
+            Some(tref) => Self::type_ref(tref),
+        };
+        Self::write_docs(w, &func.docs)?;
+        let module_prefix: String = module_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        w.write_line(format!(
+            "{} {}_{}({})",
+            return_type,
+            module_prefix,
+            name,
+            params.join(", ")
+        ))?;
+        w.continuation()?.write_line(format!(
+            "__attribute__((__import_module__(\"{}\"), __import_name__(\"{}\")));",
+            module_name, name
+        ))?;
+        Ok(())
+    }
+}
+
+impl C {
+    fn define_layout_assertions<T: Write>(
+        w: &mut PrettyWriter<T>,
+        type_: &witx::NamedType,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        let size_align = type_.mem_size_align();
+        w.write_line(format!(
+            "_Static_assert(sizeof({}) == {}, \"{}: size\");",
+            name,
+            size_align.size,
+            type_.name.as_str()
+        ))?
+        .write_line(format!(
+            "_Static_assert(_Alignof({}) == {}, \"{}: alignment\");",
+            name,
+            size_align.align,
+            type_.name.as_str()
+        ))?;
+        Ok(())
+    }
+
+    fn define_tags<T: Write>(
+        w: &mut PrettyWriter<T>,
+        type_: &witx::NamedType,
+        variant: &witx::Variant,
+        tag_type: &str,
+    ) -> Result<(), Error> {
+        for (i, case) in variant.cases.iter().enumerate() {
+            if variant.is_enum() {
+                Self::write_docs(w, &case.docs)?;
+            }
+            w.write_line(format!(
+                "#define {} (({}){})",
+                Self::constant_name(type_.name.as_str(), case.name.as_str()),
+                tag_type,
+                i
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn write_docs<T: Write>(w: &mut PrettyWriter<T>, docs: &str) -> Result<(), Error> {
+        if docs.is_empty() {
+            return Ok(());
+        }
+        w.write_line("/**")?;
+        for docs_line in docs.lines() {
+            w.write_line(format!(" * {}", docs_line))?;
+        }
+        w.write_line(" */")?;
+        Ok(())
+    }
+
+    /// Escape an identifier that is a keyword or doesn't start with a letter
+    fn name(name: &str) -> String {
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            || KEYWORDS.contains(&name)
+        {
+            format!("_{}", name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn type_name(name: &str) -> String {
+        format!("{}{}_t", PREFIX, name)
+    }
+
+    fn constant_name(type_name: &str, name: &str) -> String {
+        format!("{}{}_{}", PREFIX, type_name, name).to_uppercase()
+    }
+
+    fn builtin(builtin: &witx::BuiltinType) -> &'static str {
+        match builtin {
+            witx::BuiltinType::Char => "uint32_t",
+            witx::BuiltinType::U8 { lang_c_char: true } => "char",
+            witx::BuiltinType::U8 { lang_c_char: false } => "uint8_t",
+            witx::BuiltinType::U16 => "uint16_t",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: true,
+            } => "size_t",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: false,
+            } => "uint32_t",
+            witx::BuiltinType::U64 => "uint64_t",
+            witx::BuiltinType::S8 => "int8_t",
+            witx::BuiltinType::S16 => "int16_t",
+            witx::BuiltinType::S32 => "int32_t",
+            witx::BuiltinType::S64 => "int64_t",
+            witx::BuiltinType::F32 => "float",
+            witx::BuiltinType::F64 => "double",
+        }
+    }
+
+    fn type_ref(tref: &witx::TypeRef) -> String {
+        Self::declaration(tref, "").trim_end().to_string()
+    }
+
+    /// Declaration of `name` as a pointer to the elements of a list
+    fn declaration_in_list(element_type: &witx::TypeRef, name: &str) -> String {
+        if abi::is_string(element_type) {
+            format!("const char *{}", name)
+        } else {
+            Self::declaration(element_type, &format!("const *{}", name))
+        }
+    }
+
+    /// Declaration of `name` with the type `tref`
+    fn declaration(tref: &witx::TypeRef, name: &str) -> String {
+        match tref {
+            witx::TypeRef::Name(named_type) => {
+                format!("{} {}", Self::type_name(named_type.name.as_str()), name)
+            }
+            witx::TypeRef::Value(type_) => match type_.as_ref() {
+                witx::Type::Builtin(builtin) => format!("{} {}", Self::builtin(builtin), name),
+                witx::Type::Pointer(tref) => Self::declaration(tref, &format!("*{}", name)),
+                witx::Type::ConstPointer(tref) => {
+                    Self::declaration(tref, &format!("const *{}", name))
+                }
+                witx::Type::List(element_type) => {
+                    format!(
+                        "struct {{ {}; size_t len; }} {}",
+                        Self::declaration_in_list(element_type, "ptr"),
+                        name
+                    )
+                }
+                witx::Type::Handle(_) => format!("int32_t {}", name),
+                witx::Type::Record(record) if record.bitflags_repr().is_some() => {
+                    let repr = record.bitflags_repr().unwrap();
+                    format!("{} {}", Self::builtin(&repr.to_builtin()), name)
+                }
+                witx::Type::Record(record) => {
+                    let members: Vec<_> = record
+                        .members
+                        .iter()
+                        .map(|member| {
+                            format!(
+                                "{};",
+                                Self::declaration(&member.tref, &Self::name(member.name.as_str()))
+                            )
+                        })
+                        .collect();
+                    format!("struct {{ {} }} {}", members.join(" "), name)
+                }
+                witx::Type::Variant(variant) if variant.is_bool() => format!("uint32_t {}", name),
+                witx::Type::Variant(variant) if variant.is_enum() => format!(
+                    "{} {}",
+                    Self::builtin(&variant.tag_repr.to_builtin()),
+                    name
+                ),
+                witx::Type::Variant(variant) => {
+                    let payloads: Vec<_> = variant
+                        .cases
+                        .iter()
+                        .filter_map(|case| {
+                            case.tref.as_ref().map(|case_type| {
+                                format!(
+                                    "{};",
+                                    Self::declaration(case_type, &Self::name(case.name.as_str()))
+                                )
+                            })
+                        })
+                        .collect();
+                    format!(
+                        "struct {{ {} tag; union {{ {} }} u; }} {}",
+                        Self::builtin(&variant.tag_repr.to_builtin()),
+                        payloads.join(" "),
+                        name
+                    )
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use witx::Layout;
+
+use super::{abi, Backend};
+use crate::error::*;
+use crate::pretty_writer::PrettyWriter;
+use std::io::Write;
+
+pub struct Markdown;
+
+impl Markdown {
+    pub fn new() -> Self {
+        Markdown
+    }
+}
+
+impl Default for Markdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Backend<W> for Markdown {
+    fn header(&mut self, w: &mut PrettyWriter<W>) -> Result<(), Error> {
+        w.write_line(
+            "<!-- This file was automatically generated by as-witx - Do not edit manually. -->",
+        )?
+        .eob()?
+        .write_line("# Types")?
+        .eob()?;
+        Ok(())
+    }
+
+    fn define_type_docs(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+    ) -> Result<(), Error> {
+        let size_align = type_.mem_size_align();
+        w.write_line(format!("## `{}`", type_.name.as_str()))?.eob()?;
+        Self::write_docs(w, &type_.docs)?;
+        w.write_line(format!(
+            "Size: {}, alignment: {}",
+            Self::bytes(size_align.size),
+            Self::bytes(size_align.align)
+        ))?
+        .eob()?;
+        Ok(())
+    }
+
+    fn define_alias(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _type_: &witx::NamedType,
+        other_type: &witx::NamedType,
+    ) -> Result<(), Error> {
+        w.write_line(format!("Alias for {}.", Self::named_type(other_type)))?;
+        Ok(())
+    }
+
+    fn define_handle(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _type_: &witx::NamedType,
+        _handle: &witx::HandleDatatype,
+    ) -> Result<(), Error> {
+        w.write_line("Handle.")?;
+        Ok(())
+    }
+
+    fn define_builtin(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _type_: &witx::NamedType,
+        builtin: &witx::BuiltinType,
+    ) -> Result<(), Error> {
+        w.write_line(format!("Builtin type `{}`.", Self::builtin(builtin)))?;
+        Ok(())
+    }
+
+    fn define_record(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _type_: &witx::NamedType,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error> {
+        if let Some(repr) = record.bitflags_repr() {
+            w.write_line(format!(
+                "Flags, stored as `{}`.",
+                Self::builtin(&repr.to_builtin())
+            ))?
+            .eob()?;
+            w.write_line("| Bit | Flag | Description |")?
+                .write_line("| --- | ---- | ----------- |")?;
+            for (i, member) in record.members.iter().enumerate() {
+                w.write_line(format!(
+                    "| `1 << {}` | `{}` | {} |",
+                    i,
+                    member.name.as_str(),
+                    Self::table_docs(&member.docs)
+                ))?;
+            }
+            return Ok(());
+        }
+        w.write_line("Record.")?.eob()?;
+        w.write_line("| Offset | Member | Type | Description |")?
+            .write_line("| ------ | ------ | ---- | ----------- |")?;
+        for member_layout in record.member_layout() {
+            let member = member_layout.member;
+            w.write_line(format!(
+                "| {} | `{}` | {} | {} |",
+                member_layout.offset,
+                member.name.as_str(),
+                Self::type_ref(&member.tref),
+                Self::table_docs(&member.docs)
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_variant(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _type_: &witx::NamedType,
+        variant: &witx::Variant,
+    ) -> Result<(), Error> {
+        let tag_type = Self::builtin(&variant.tag_repr.to_builtin());
+        if variant.is_enum() {
+            w.write_line(format!("Enumeration, stored as `{}`.", tag_type))?
+                .eob()?;
+            w.write_line("| Value | Case | Description |")?
+                .write_line("| ----- | ---- | ----------- |")?;
+            for (i, case) in variant.cases.iter().enumerate() {
+                w.write_line(format!(
+                    "| {} | `{}` | {} |",
+                    i,
+                    case.name.as_str(),
+                    Self::table_docs(&case.docs)
+                ))?;
+            }
+            return Ok(());
+        }
+        w.write_line(format!(
+            "Variant, with a `{}` tag and a payload at offset {}.",
+            tag_type,
+            variant.payload_offset()
+        ))?
+        .eob()?;
+        w.write_line("| Tag | Case | Payload | Description |")?
+            .write_line("| --- | ---- | ------- | ----------- |")?;
+        for (i, case) in variant.cases.iter().enumerate() {
+            let payload = match &case.tref {
+                None => "-".to_string(),
+                Some(case_type) => Self::type_ref(case_type),
+            };
+            w.write_line(format!(
+                "| {} | `{}` | {} | {} |",
+                i,
+                case.name.as_str(),
+                payload,
+                Self::table_docs(&case.docs)
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_list(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _type_: &witx::NamedType,
+        element_type: &witx::TypeRef,
+    ) -> Result<(), Error> {
+        w.write_line(format!("List of {}.", Self::type_ref(element_type)))?;
+        Ok(())
+    }
+
+    fn define_pointer(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _type_: &witx::NamedType,
+        pointee_type: &witx::TypeRef,
+        is_const: bool,
+    ) -> Result<(), Error> {
+        let kind = if is_const { "Constant pointer" } else { "Pointer" };
+        w.write_line(format!("{} to {}.", kind, Self::type_ref(pointee_type)))?;
+        Ok(())
+    }
+
+    fn define_constants(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        constants: &[&witx::Constant],
+    ) -> Result<(), Error> {
+        w.write_line("Constants:")?.eob()?;
+        w.write_line("| Name | Value | Description |")?
+            .write_line("| ---- | ----- | ----------- |")?;
+        for constant in constants {
+            w.write_line(format!(
+                "| `{}` | `{}` | {} |",
+                constant.name.as_str(),
+                abi::constant_value(type_, constant.value),
+                Self::table_docs(&constant.docs)
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_module(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module: &witx::Module,
+    ) -> Result<(), Error> {
+        w.write_line(format!("# Module `{}`", module.name.as_str()))?
+            .eob()?;
+        Self::write_docs(w, &module.docs)?;
+        Ok(())
+    }
+
+    fn define_func(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        _module_name: &str,
+        func: &witx::InterfaceFunc,
+    ) -> Result<(), Error> {
+        w.write_line(format!("## `{}`", func.name.as_str()))?.eob()?;
+        Self::write_docs(w, &func.docs)?;
+        if func.noreturn {
+            w.write_line("This function never returns.")?.eob()?;
+        }
+        Self::define_params(w, "Parameters", &func.params)?;
+        if !func.params.is_empty() && !func.results.is_empty() {
+            w.eob()?;
+        }
+        Self::define_params(w, "Results", &func.results)?;
+        Ok(())
+    }
+}
+
+impl Markdown {
+    fn define_params<T: Write>(
+        w: &mut PrettyWriter<T>,
+        title: &str,
+        params: &[witx::InterfaceFuncParam],
+    ) -> Result<(), Error> {
+        if params.is_empty() {
+            return Ok(());
+        }
+        w.write_line(format!("{}:", title))?.eob()?;
+        w.write_line("| Name | Type | Description |")?
+            .write_line("| ---- | ---- | ----------- |")?;
+        for param in params {
+            w.write_line(format!(
+                "| `{}` | {} | {} |",
+                param.name.as_str(),
+                Self::type_ref(&param.tref),
+                Self::table_docs(&param.docs)
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn write_docs<T: Write>(w: &mut PrettyWriter<T>, docs: &str) -> Result<(), Error> {
+        if docs.is_empty() {
+            return Ok(());
+        }
+        w.write_lines(docs)?.eob()?;
+        Ok(())
+    }
+
+    fn bytes(count: usize) -> String {
+        if count == 1 {
+            "1 byte".to_string()
+        } else {
+            format!("{} bytes", count)
+        }
+    }
+
+    /// Documentation, as a single line that can be used within a table cell
+    fn table_docs(docs: &str) -> String {
+        let lines: Vec<_> = docs
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        lines.join(" ").replace('|', "\\|")
+    }
+
+    fn named_type(named_type: &witx::NamedType) -> String {
+        let name = named_type.name.as_str();
+        format!("[`{}`](#{})", name, name)
+    }
+
+    fn builtin(builtin: &witx::BuiltinType) -> &'static str {
+        match builtin {
+            witx::BuiltinType::Char => "char",
+            witx::BuiltinType::U8 { .. } => "u8",
+            witx::BuiltinType::U16 => "u16",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: true,
+            } => "usize",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: false,
+            } => "u32",
+            witx::BuiltinType::U64 => "u64",
+            witx::BuiltinType::S8 => "s8",
+            witx::BuiltinType::S16 => "s16",
+            witx::BuiltinType::S32 => "s32",
+            witx::BuiltinType::S64 => "s64",
+            witx::BuiltinType::F32 => "f32",
+            witx::BuiltinType::F64 => "f64",
+        }
+    }
+
+    fn type_ref(tref: &witx::TypeRef) -> String {
+        match tref {
+            witx::TypeRef::Name(named_type) => Self::named_type(named_type),
+            witx::TypeRef::Value(type_) => Self::anonymous_type(type_),
+        }
+    }
+
+    fn anonymous_type(type_: &witx::Type) -> String {
+        match type_ {
+            witx::Type::Builtin(builtin) => format!("`{}`", Self::builtin(builtin)),
+            witx::Type::Pointer(tref) => format!("pointer<{}>", Self::type_ref(tref)),
+            witx::Type::ConstPointer(tref) => {
+                format!("const_pointer<{}>", Self::type_ref(tref))
+            }
+            witx::Type::List(element_type) => match element_type.type_().as_ref() {
+                witx::Type::Builtin(witx::BuiltinType::Char) => "`string`".to_string(),
+                _ => format!("list<{}>", Self::type_ref(element_type)),
+            },
+            witx::Type::Handle(_) => "`handle`".to_string(),
+            witx::Type::Record(record) => {
+                let members: Vec<_> = record
+                    .members
+                    .iter()
+                    .map(|member| Self::type_ref(&member.tref))
+                    .collect();
+                format!("tuple<{}>", members.join(", "))
+            }
+            witx::Type::Variant(variant) if variant.is_bool() => "`bool`".to_string(),
+            witx::Type::Variant(variant) => match variant.as_expected() {
+                Some((ok, err)) => {
+                    let ok = ok.map_or("-".to_string(), Self::type_ref);
+                    let err = err.map_or("-".to_string(), Self::type_ref);
+                    format!("expected<{}, {}>", ok, err)
+                }
+                None => {
+                    let cases: Vec<_> = variant
+                        .cases
+                        .iter()
+                        .map(|case| case.name.as_str())
+                        .collect();
+                    format!("`variant<{}>`", cases.join(", "))
+                }
+            },
+        }
+    }
+}
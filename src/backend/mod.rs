@@ -0,0 +1,178 @@
+mod abi;
+mod assemblyscript;
+mod c;
+mod markdown;
+mod rust;
+mod zig;
+
+pub use self::assemblyscript::AssemblyScript;
+pub use self::c::C;
+pub use self::markdown::Markdown;
+pub use self::rust::Rust;
+pub use self::zig::Zig;
+
+use crate::error::*;
+use crate::pretty_writer::PrettyWriter;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Language-specific code generation hooks.
+///
+/// `Generator` walks the witx document and calls into a `Backend` for every
+/// type and function it encounters.
+pub trait Backend<W: Write> {
+    /// Output the preamble of the generated file
+    fn header(&mut self, w: &mut PrettyWriter<W>) -> Result<(), Error>;
+
+    /// Output the end of the generated file
+    fn footer(&mut self, _w: &mut PrettyWriter<W>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Output the documentation of a named type, before its definition
+    fn define_type_docs(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+    ) -> Result<(), Error>;
+
+    /// Define a type as an alias of another named type
+    fn define_alias(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        other_type: &witx::NamedType,
+    ) -> Result<(), Error>;
+
+    fn define_handle(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        handle: &witx::HandleDatatype,
+    ) -> Result<(), Error>;
+
+    fn define_builtin(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        builtin: &witx::BuiltinType,
+    ) -> Result<(), Error>;
+
+    fn define_record(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error>;
+
+    fn define_variant(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        variant: &witx::Variant,
+    ) -> Result<(), Error>;
+
+    fn define_list(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        element_type: &witx::TypeRef,
+    ) -> Result<(), Error>;
+
+    fn define_pointer(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        pointee_type: &witx::TypeRef,
+        is_const: bool,
+    ) -> Result<(), Error>;
+
+    /// Define the witx constants of a type, right after the type itself
+    fn define_constants(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        constants: &[&witx::Constant],
+    ) -> Result<(), Error>;
+
+    /// Output the preamble of a module, before its functions
+    fn define_module(&mut self, w: &mut PrettyWriter<W>, module: &witx::Module)
+        -> Result<(), Error>;
+
+    /// Define a function imported from `module_name`
+    fn define_func(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module_name: &str,
+        func: &witx::InterfaceFunc,
+    ) -> Result<(), Error>;
+
+    /// Output the preamble of a file containing a module, importing the types
+    /// it uses from `types_file`, when the output is split into several files
+    fn define_imports(
+        &mut self,
+        _w: &mut PrettyWriter<W>,
+        _types_file: &str,
+        _type_names: &[String],
+    ) -> Result<(), Error> {
+        Err(Error::Usage(
+            "This target doesn't support splitting the output".to_string(),
+        ))
+    }
+
+    /// Output a file re-exporting everything from the other files
+    fn define_index(&mut self, _w: &mut PrettyWriter<W>, _files: &[String]) -> Result<(), Error> {
+        Err(Error::Usage(
+            "This target doesn't support splitting the output".to_string(),
+        ))
+    }
+}
+
+/// Options shared by all backends, ignored by backends they don't apply to
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Generate high-level wrappers around the raw imported functions
+    pub wrappers: bool,
+}
+
+/// Output language
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Target {
+    #[default]
+    AssemblyScript,
+    Zig,
+    Markdown,
+    Rust,
+    C,
+}
+
+impl Target {
+    /// Names accepted by `Target::from_str`
+    pub const NAMES: &'static [&'static str] = &["assemblyscript", "zig", "markdown", "rust", "c"];
+
+    /// Create a new backend for this target
+    pub fn backend<W: Write>(self, options: &Options) -> Box<dyn Backend<W>> {
+        match self {
+            Target::AssemblyScript => Box::new(AssemblyScript::with_options(options)),
+            Target::Zig => Box::new(Zig::new()),
+            Target::Markdown => Box::new(Markdown::new()),
+            Target::Rust => Box::new(Rust::new()),
+            Target::C => Box::new(C::new()),
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "assemblyscript" => Ok(Target::AssemblyScript),
+            "zig" => Ok(Target::Zig),
+            "markdown" => Ok(Target::Markdown),
+            "rust" => Ok(Target::Rust),
+            "c" => Ok(Target::C),
+            _ => Err(format!("Unsupported target: [{}]", s)),
+        }
+    }
+}
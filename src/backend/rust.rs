@@ -0,0 +1,501 @@
+use super::abi::{self, ParamKind, Signature};
+use super::Backend;
+use crate::error::*;
+use crate::pretty_writer::PrettyWriter;
+use std::io::Write;
+use std::rc::Rc;
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe",
+    "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+pub struct Rust {
+    /// Anonymous records and variants, with the name they are given, that
+    /// are still to be defined
+    anonymous_types: Vec<(String, Rc<witx::Type>)>,
+}
+
+impl Rust {
+    pub fn new() -> Self {
+        Rust {
+            anonymous_types: vec![],
+        }
+    }
+}
+
+impl Default for Rust {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Backend<W> for Rust {
+    fn header(&mut self, w: &mut PrettyWriter<W>) -> Result<(), Error> {
+        w.write_line("//")?
+            .write_line(
+                "// This file was automatically generated by as-witx - Do not edit manually.",
+            )?
+            .write_line("//")?;
+        w.write_lines(
+            "
+/// A list, as stored in records and variants
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct WasiList<T> {
+    pub ptr: *const T,
+    pub len: usize,
+}
+",
+        )?
+        .eob()?;
+        Ok(())
+    }
+
+    fn define_type_docs(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+    ) -> Result<(), Error> {
+        Self::write_docs(w, &type_.docs)
+    }
+
+    fn define_alias(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        other_type: &witx::NamedType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub type {} = {};",
+            Self::type_name(type_.name.as_str()),
+            Self::type_name(other_type.name.as_str())
+        ))?;
+        Ok(())
+    }
+
+    fn define_handle(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        _handle: &witx::HandleDatatype,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub type {} = u32;",
+            Self::type_name(type_.name.as_str())
+        ))?;
+        Ok(())
+    }
+
+    fn define_builtin(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        builtin: &witx::BuiltinType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub type {} = {};",
+            Self::type_name(type_.name.as_str()),
+            Self::builtin(builtin)
+        ))?;
+        Ok(())
+    }
+
+    fn define_record(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        if let Some(repr) = record.bitflags_repr() {
+            w.write_line(format!(
+                "pub type {} = {};",
+                name,
+                Self::builtin(&repr.to_builtin())
+            ))?;
+            for (i, member) in record.members.iter().enumerate() {
+                Self::write_docs(w, &member.docs)?;
+                w.write_line(format!(
+                    "pub const {}_{}: {} = 1 << {};",
+                    type_.name.as_str().to_uppercase(),
+                    member.name.as_str().to_uppercase(),
+                    name,
+                    i
+                ))?;
+            }
+            return Ok(());
+        }
+        self.define_struct(w, type_.name.as_str(), record)?;
+        self.define_anonymous_types(w)
+    }
+
+    fn define_variant(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        variant: &witx::Variant,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        if variant.is_bool() {
+            w.write_line(format!("pub type {} = {};", name, Self::BOOL))?;
+            return Ok(());
+        }
+        let tag_type = Self::builtin(&variant.tag_repr.to_builtin());
+        if variant.is_enum() {
+            // A Rust enum can't hold values the host may return that it doesn't know about
+            w.write_line("#[repr(transparent)]")?
+                .write_line("#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]")?
+                .write_line(format!("pub struct {}(pub {});", name, tag_type))?
+                .eob()?;
+            w.write_line(format!("impl {} {{", name))?;
+            {
+                let mut w = w.new_block();
+                for (i, case) in variant.cases.iter().enumerate() {
+                    Self::write_docs(&mut w, &case.docs)?;
+                    w.write_line(format!(
+                        "pub const {}: {} = {}({});",
+                        Self::constant_name(case.name.as_str()),
+                        name,
+                        name,
+                        i
+                    ))?;
+                }
+            }
+            w.write_line("}")?;
+            return Ok(());
+        }
+        self.define_enum(w, type_.name.as_str(), variant)?;
+        self.define_anonymous_types(w)
+    }
+
+    fn define_list(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        element_type: &witx::TypeRef,
+    ) -> Result<(), Error> {
+        let list = self.list(element_type, type_.name.as_str());
+        w.write_line(format!(
+            "pub type {} = {};",
+            Self::type_name(type_.name.as_str()),
+            list
+        ))?;
+        self.define_anonymous_types(w)
+    }
+
+    fn define_pointer(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        _pointee_type: &witx::TypeRef,
+        _is_const: bool,
+    ) -> Result<(), Error> {
+        let pointer = self.type_ref(&type_.tref, type_.name.as_str());
+        w.write_line(format!(
+            "pub type {} = {};",
+            Self::type_name(type_.name.as_str()),
+            pointer
+        ))?;
+        self.define_anonymous_types(w)
+    }
+
+    fn define_constants(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        constants: &[&witx::Constant],
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        for constant in constants {
+            let value = match type_.type_().as_ref() {
+                witx::Type::Variant(variant) if variant.is_bool() => constant.value.to_string(),
+                witx::Type::Variant(variant) => match variant.cases.get(constant.value as usize) {
+                    Some(case) => format!("{}::{}", name, Self::constant_name(case.name.as_str())),
+                    None => format!("{}({})", name, constant.value),
+                },
+                _ => abi::constant_value(type_, constant.value),
+            };
+            Self::write_docs(w, &constant.docs)?;
+            w.write_line(format!(
+                "pub const {}_{}: {} = {};",
+                type_.name.as_str().to_uppercase(),
+                constant.name.as_str().to_uppercase(),
+                name,
+                value
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_module(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module: &witx::Module,
+    ) -> Result<(), Error> {
+        w.eob()?
+            .write_line(format!(
+                "// ----------------------[{}]----------------------",
+                module.name.as_str()
+            ))?
+            .eob()?;
+        Ok(())
+    }
+
+    fn define_func(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module_name: &str,
+        func: &witx::InterfaceFunc,
+    ) -> Result<(), Error> {
+        let name = func.name.as_str();
+        let signature = Signature::new(func);
+        let mut params = vec![];
+        for param in &signature.params {
+            let type_name = format!("{}_{}", name, param.name);
+            let param_type = match &param.kind {
+                ParamKind::Value(tref) if abi::is_passed_by_reference(tref) => {
+                    format!("*const {}", self.type_ref(tref, &type_name))
+                }
+                ParamKind::Value(tref) => self.type_ref(tref, &type_name),
+                ParamKind::ListPtr(element_type) if abi::is_string(element_type) => {
+                    "*const u8".to_string()
+                }
+                ParamKind::ListPtr(element_type) => {
+                    format!("*const {}", self.type_ref(element_type, &type_name))
+                }
+                ParamKind::ListLen => "usize".to_string(),
+                ParamKind::Out(tref) => format!("*mut {}", self.type_ref(tref, &type_name)),
+            };
+            params.push(format!("{}: {}", Self::name(&param.name), param_type));
+        }
+        let return_type = match signature.result {
+            _ if func.noreturn => " -> !".to_string(),
+            None => "".to_string(),
+            Some(tref) => format!(" -> {}", self.type_ref(tref, &format!("{}_result", name))),
+        };
+        w.write_line(format!("#[link(wasm_import_module = \"{}\")]", module_name))?
+            .write_line("extern \"C\" {")?;
+        {
+            let mut w = w.new_block();
+            Self::write_docs(&mut w, &func.docs)?;
+            w.write_line(format!(
+                "pub fn {}({}){};",
+                Self::name(name),
+                params.join(", "),
+                return_type
+            ))?;
+        }
+        w.write_line("}")?;
+        self.define_anonymous_types(w)
+    }
+}
+
+impl Rust {
+    /// witx booleans are 32-bit, and any other value than 0 or 1 would be UB as a `bool`
+    const BOOL: &'static str = "u32";
+
+    fn define_struct<T: Write>(
+        &mut self,
+        w: &mut PrettyWriter<T>,
+        name: &str,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error> {
+        let type_name = Self::type_name(name);
+        w.write_line("#[repr(C)]")?
+            .write_line("#[derive(Clone, Copy, Debug)]")?;
+        if record.is_tuple() {
+            let members: Vec<_> = record
+                .members
+                .iter()
+                .map(|member| {
+                    let member_name = format!("{}_{}", name, member.name.as_str());
+                    format!("pub {}", self.type_ref(&member.tref, &member_name))
+                })
+                .collect();
+            w.write_line(format!("pub struct {}({});", type_name, members.join(", ")))?;
+            return Ok(());
+        }
+        w.write_line(format!("pub struct {} {{", type_name))?;
+        {
+            let mut w = w.new_block();
+            for member in &record.members {
+                let member_name = format!("{}_{}", name, member.name.as_str());
+                Self::write_docs(&mut w, &member.docs)?;
+                w.write_line(format!(
+                    "pub {}: {},",
+                    Self::name(member.name.as_str()),
+                    self.type_ref(&member.tref, &member_name)
+                ))?;
+            }
+        }
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    fn define_enum<T: Write>(
+        &mut self,
+        w: &mut PrettyWriter<T>,
+        name: &str,
+        variant: &witx::Variant,
+    ) -> Result<(), Error> {
+        let tag_type = Self::builtin(&variant.tag_repr.to_builtin());
+        w.write_line(format!("#[repr(C, {})]", tag_type))?
+            .write_line("#[derive(Clone, Copy, Debug)]")?;
+        w.write_line(format!("pub enum {} {{", Self::type_name(name)))?;
+        {
+            let mut w = w.new_block();
+            for case in &variant.cases {
+                Self::write_docs(&mut w, &case.docs)?;
+                let case_name = Self::type_name(case.name.as_str());
+                match &case.tref {
+                    None => {
+                        w.write_line(format!("{},", case_name))?;
+                    }
+                    Some(case_type) => {
+                        let payload_name = format!("{}_{}", name, case.name.as_str());
+                        w.write_line(format!(
+                            "{}({}),",
+                            case_name,
+                            self.type_ref(case_type, &payload_name)
+                        ))?;
+                    }
+                }
+            }
+        }
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    /// Define the anonymous types used by the last definition, after it.
+    /// Rust has no anonymous structs or enums, and tuples have no fixed layout.
+    fn define_anonymous_types<T: Write>(&mut self, w: &mut PrettyWriter<T>) -> Result<(), Error> {
+        while !self.anonymous_types.is_empty() {
+            let (name, type_) = self.anonymous_types.remove(0);
+            w.eob()?;
+            match type_.as_ref() {
+                witx::Type::Record(record) => self.define_struct(w, &name, record)?,
+                witx::Type::Variant(variant) => self.define_enum(w, &name, variant)?,
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_docs<T: Write>(w: &mut PrettyWriter<T>, docs: &str) -> Result<(), Error> {
+        for docs_line in docs.lines() {
+            w.write_line(format!("/// {}", docs_line))?;
+        }
+        Ok(())
+    }
+
+    /// Escape an identifier that is a keyword or doesn't start with a letter
+    fn name(name: &str) -> String {
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            format!("_{}", name)
+        } else if KEYWORDS.contains(&name) {
+            format!("r#{}", name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn constant_name(name: &str) -> String {
+        Self::name(&name.to_uppercase())
+    }
+
+    /// Convert a witx name to an `UpperCamelCase` type name
+    fn type_name(name: &str) -> String {
+        let mut type_name = String::with_capacity(name.len());
+        let mut upper = true;
+        for c in name.chars() {
+            if c == '_' {
+                upper = true;
+            } else if upper {
+                type_name.extend(c.to_uppercase());
+                upper = false;
+            } else {
+                type_name.push(c);
+            }
+        }
+        Self::name(&type_name)
+    }
+
+    fn builtin(builtin: &witx::BuiltinType) -> &'static str {
+        match builtin {
+            // Not every 32-bit value is a valid `char`
+            witx::BuiltinType::Char => "u32",
+            witx::BuiltinType::U8 { .. } => "u8",
+            witx::BuiltinType::U16 => "u16",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: true,
+            } => "usize",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: false,
+            } => "u32",
+            witx::BuiltinType::U64 => "u64",
+            witx::BuiltinType::S8 => "i8",
+            witx::BuiltinType::S16 => "i16",
+            witx::BuiltinType::S32 => "i32",
+            witx::BuiltinType::S64 => "i64",
+            witx::BuiltinType::F32 => "f32",
+            witx::BuiltinType::F64 => "f64",
+        }
+    }
+
+    /// `name` is the name given to the element type, if it is anonymous
+    fn list(&mut self, element_type: &witx::TypeRef, name: &str) -> String {
+        if abi::is_string(element_type) {
+            "WasiList<u8>".to_string()
+        } else {
+            format!(
+                "WasiList<{}>",
+                self.type_ref(element_type, &format!("{}_element", name))
+            )
+        }
+    }
+
+    /// `name` is the name given to the type if it is an anonymous record or
+    /// variant, which is then defined by `define_anonymous_types()`
+    fn type_ref(&mut self, tref: &witx::TypeRef, name: &str) -> String {
+        match tref {
+            witx::TypeRef::Name(named_type) => Self::type_name(named_type.name.as_str()),
+            witx::TypeRef::Value(type_) => self.anonymous_type(type_, name),
+        }
+    }
+
+    fn anonymous_type(&mut self, type_: &Rc<witx::Type>, name: &str) -> String {
+        match type_.as_ref() {
+            witx::Type::Builtin(builtin) => Self::builtin(builtin).to_string(),
+            witx::Type::Pointer(tref) => {
+                format!("*mut {}", self.type_ref(tref, &format!("{}_pointee", name)))
+            }
+            witx::Type::ConstPointer(tref) => {
+                format!(
+                    "*const {}",
+                    self.type_ref(tref, &format!("{}_pointee", name))
+                )
+            }
+            witx::Type::List(element_type) => self.list(element_type, name),
+            witx::Type::Handle(_) => "u32".to_string(),
+            witx::Type::Record(record) => match record.bitflags_repr() {
+                Some(repr) => Self::builtin(&repr.to_builtin()).to_string(),
+                None => {
+                    self.anonymous_types.push((name.to_string(), type_.clone()));
+                    Self::type_name(name)
+                }
+            },
+            witx::Type::Variant(variant) if variant.is_bool() => Self::BOOL.to_string(),
+            witx::Type::Variant(variant) if variant.is_enum() => {
+                Self::builtin(&variant.tag_repr.to_builtin()).to_string()
+            }
+            witx::Type::Variant(_) => {
+                self.anonymous_types.push((name.to_string(), type_.clone()));
+                Self::type_name(name)
+            }
+        }
+    }
+}
@@ -0,0 +1,722 @@
+use super::abi::{self, ParamKind, Signature};
+use super::Backend;
+use crate::error::*;
+use crate::pretty_writer::PrettyWriter;
+use std::collections::HashSet;
+use std::io::Write;
+
+const KEYWORDS: &[&str] = &[
+    "addrspace",
+    "align",
+    "allowzero",
+    "and",
+    "anyframe",
+    "anytype",
+    "asm",
+    "async",
+    "await",
+    "break",
+    "callconv",
+    "catch",
+    "comptime",
+    "const",
+    "continue",
+    "defer",
+    "else",
+    "enum",
+    "errdefer",
+    "error",
+    "export",
+    "extern",
+    "fn",
+    "for",
+    "if",
+    "inline",
+    "linksection",
+    "noalias",
+    "noinline",
+    "nosuspend",
+    "opaque",
+    "or",
+    "orelse",
+    "packed",
+    "pub",
+    "resume",
+    "return",
+    "struct",
+    "suspend",
+    "switch",
+    "test",
+    "threadlocal",
+    "try",
+    "type",
+    "union",
+    "unreachable",
+    "usingnamespace",
+    "var",
+    "volatile",
+    "while",
+];
+
+pub struct Zig {
+    error_sets: HashSet<String>,
+}
+
+impl Zig {
+    pub fn new() -> Self {
+        Zig {
+            error_sets: HashSet::new(),
+        }
+    }
+}
+
+impl Default for Zig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Backend<W> for Zig {
+    fn header(&mut self, w: &mut PrettyWriter<W>) -> Result<(), Error> {
+        w.write_line("//")?
+            .write_line(
+                "// This file was automatically generated by as-witx - Do not edit manually.",
+            )?
+            .write_line("//")?
+            .eob()?;
+        Ok(())
+    }
+
+    fn define_type_docs(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+    ) -> Result<(), Error> {
+        Self::write_docs(w, &type_.docs)
+    }
+
+    fn define_alias(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        other_type: &witx::NamedType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub const {} = {};",
+            Self::type_name(type_.name.as_str()),
+            Self::type_name(other_type.name.as_str())
+        ))?;
+        Ok(())
+    }
+
+    fn define_handle(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        _handle: &witx::HandleDatatype,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub const {} = i32;",
+            Self::type_name(type_.name.as_str())
+        ))?;
+        Ok(())
+    }
+
+    fn define_builtin(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        builtin: &witx::BuiltinType,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub const {} = {};",
+            Self::type_name(type_.name.as_str()),
+            Self::builtin(builtin)
+        ))?;
+        Ok(())
+    }
+
+    fn define_record(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        record: &witx::RecordDatatype,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        if let Some(repr) = record.bitflags_repr() {
+            w.write_line(format!(
+                "pub const {} = {};",
+                name,
+                Self::builtin(&repr.to_builtin())
+            ))?;
+            for (i, member) in record.members.iter().enumerate() {
+                Self::write_docs(w, &member.docs)?;
+                w.write_line(format!(
+                    "pub const {}_{}: {} = 1 << {};",
+                    type_.name.as_str().to_uppercase(),
+                    member.name.as_str().to_uppercase(),
+                    name,
+                    i
+                ))?;
+            }
+            return Ok(());
+        }
+        w.write_line(format!("pub const {} = extern struct {{", name))?;
+        {
+            let mut w = w.new_block();
+            for member in &record.members {
+                Self::write_docs(&mut w, &member.docs)?;
+                w.write_line(format!(
+                    "{}: {},",
+                    Self::name(member.name.as_str()),
+                    Self::type_ref(&member.tref)
+                ))?;
+            }
+        }
+        w.write_line("};")?;
+        Ok(())
+    }
+
+    fn define_variant(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        variant: &witx::Variant,
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        if variant.is_bool() {
+            w.write_line(format!("pub const {} = {};", name, Self::BOOL))?;
+            return Ok(());
+        }
+        let tag_type = Self::builtin(&variant.tag_repr.to_builtin());
+        if variant.is_enum() {
+            w.write_line(format!("pub const {} = enum({}) {{", name, tag_type))?;
+            Self::define_enum_cases(&mut w.new_block(), variant)?;
+            w.write_line("};")?;
+            return Ok(());
+        }
+        w.write_line(format!("pub const {} = extern struct {{", name))?;
+        {
+            let mut w = w.new_block();
+            w.write_line("tag: Tag,")?
+                .write_line("payload: Payload,")?
+                .eob()?;
+            w.write_line(format!("pub const Tag = enum({}) {{", tag_type))?;
+            Self::define_enum_cases(&mut w.new_block(), variant)?;
+            w.write_line("};")?.eob()?;
+            w.write_line("pub const Payload = extern union {")?;
+            {
+                let mut w = w.new_block();
+                for case in &variant.cases {
+                    if let Some(case_type) = &case.tref {
+                        w.write_line(format!(
+                            "{}: {},",
+                            Self::name(case.name.as_str()),
+                            Self::type_ref(case_type)
+                        ))?;
+                    }
+                }
+            }
+            w.write_line("};")?;
+        }
+        w.write_line("};")?;
+        Ok(())
+    }
+
+    fn define_list(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        element_type: &witx::TypeRef,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub const {} = {};",
+            Self::type_name(type_.name.as_str()),
+            Self::list_struct(element_type)
+        ))?;
+        Ok(())
+    }
+
+    fn define_pointer(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        _pointee_type: &witx::TypeRef,
+        _is_const: bool,
+    ) -> Result<(), Error> {
+        w.write_line(format!(
+            "pub const {} = {};",
+            Self::type_name(type_.name.as_str()),
+            Self::type_ref(&type_.tref)
+        ))?;
+        Ok(())
+    }
+
+    fn define_constants(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        type_: &witx::NamedType,
+        constants: &[&witx::Constant],
+    ) -> Result<(), Error> {
+        let name = Self::type_name(type_.name.as_str());
+        for constant in constants {
+            let (constant_type, value) = match type_.type_().as_ref() {
+                witx::Type::Variant(variant) if variant.is_bool() => {
+                    (name.clone(), constant.value.to_string())
+                }
+                witx::Type::Variant(variant) => match variant.cases.get(constant.value as usize) {
+                    Some(case) => (name.clone(), format!(".{}", Self::name(case.name.as_str()))),
+                    None => (name.clone(), format!("@enumFromInt({})", constant.value)),
+                },
+                _ => (name.clone(), abi::constant_value(type_, constant.value)),
+            };
+            Self::write_docs(w, &constant.docs)?;
+            w.write_line(format!(
+                "pub const {}_{}: {} = {};",
+                type_.name.as_str().to_uppercase(),
+                constant.name.as_str().to_uppercase(),
+                constant_type,
+                value
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn define_module(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module: &witx::Module,
+    ) -> Result<(), Error> {
+        w.eob()?
+            .write_line(format!(
+                "// ----------------------[{}]----------------------",
+                module.name.as_str()
+            ))?
+            .eob()?;
+        Ok(())
+    }
+
+    fn define_func(
+        &mut self,
+        w: &mut PrettyWriter<W>,
+        module_name: &str,
+        func: &witx::InterfaceFunc,
+    ) -> Result<(), Error> {
+        let name = func.name.as_str();
+        let signature = Signature::new(func);
+        let params: Vec<_> = signature
+            .params
+            .iter()
+            .map(|param| {
+                let param_type = match &param.kind {
+                    ParamKind::Value(tref) if abi::is_passed_by_reference(tref) => {
+                        format!("*const {}", Self::param_type(name, &param.name, tref))
+                    }
+                    ParamKind::Value(tref) => Self::param_type(name, &param.name, tref),
+                    ParamKind::ListPtr(element_type) if abi::is_string(element_type) => {
+                        "[*]const u8".to_string()
+                    }
+                    ParamKind::ListPtr(element_type) => {
+                        let list_name = param.name.strip_suffix("_ptr").unwrap_or(&param.name);
+                        format!(
+                            "[*]const {}",
+                            Self::param_type(name, &format!("{}_element", list_name), element_type)
+                        )
+                    }
+                    ParamKind::ListLen => "usize".to_string(),
+                    ParamKind::Out(tref) => format!("*{}", Self::type_ref(tref)),
+                };
+                format!("{}: {}", Self::name(&param.name), param_type)
+            })
+            .collect();
+        let return_type = match signature.result {
+            _ if func.noreturn => "noreturn".to_string(),
+            None => "void".to_string(),
+            Some(tref) => Self::type_ref(tref),
+        };
+        Self::define_param_types(w, func)?;
+        Self::write_docs(w, &func.docs)?;
+        w.write_line(format!(
+            "pub extern \"{}\" fn {}({}) {};",
+            module_name,
+            Self::name(name),
+            params.join(", "),
+            return_type
+        ))?;
+        if signature.returns_error {
+            self.define_func_wrapper(w, func, &signature)?;
+        }
+        Ok(())
+    }
+}
+
+impl Zig {
+    /// witx booleans are 32-bit, and Zig's `bool` is a single byte
+    const BOOL: &'static str = "u32";
+
+    /// Define a function returning an error union, on top of a raw import
+    fn define_func_wrapper<T: Write>(
+        &mut self,
+        w: &mut PrettyWriter<T>,
+        func: &witx::InterfaceFunc,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        let error_type = match signature.result {
+            Some(witx::TypeRef::Name(error_type)) => error_type,
+            _ => return Ok(()),
+        };
+        let error_variant = match error_type.type_().as_ref() {
+            witx::Type::Variant(variant) if variant.is_enum() => variant,
+            _ => return Ok(()),
+        };
+        let error_name = error_type.name.as_str();
+        if self.error_sets.insert(error_name.to_string()) {
+            w.eob()?;
+            Self::define_error_set(w, error_name, error_variant)?;
+        }
+
+        let name = func.name.as_str();
+        let mut params = vec![];
+        let mut args = vec![];
+        for param in &func.params {
+            let param_name = Self::name(param.name.as_str());
+            match param.tref.type_().as_ref() {
+                witx::Type::List(element_type) => {
+                    let element_name = format!("{}_element", param.name.as_str());
+                    params.push(format!(
+                        "{}: {}",
+                        param_name,
+                        Self::list(name, &element_name, element_type)
+                    ));
+                    args.push(format!("{}.ptr", param_name));
+                    args.push(format!("{}.len", param_name));
+                }
+                _ if abi::is_passed_by_reference(&param.tref) => {
+                    params.push(format!(
+                        "{}: *const {}",
+                        param_name,
+                        Self::param_type(name, param.name.as_str(), &param.tref)
+                    ));
+                    args.push(param_name);
+                }
+                _ => {
+                    params.push(format!(
+                        "{}: {}",
+                        param_name,
+                        Self::param_type(name, param.name.as_str(), &param.tref)
+                    ));
+                    args.push(param_name);
+                }
+            }
+        }
+        let ok_type = match signature.ok {
+            None => "void".to_string(),
+            // Every struct, union and enum literal is a distinct type, so
+            // anonymous ones need a name to be used in several places
+            Some(witx::TypeRef::Value(type_)) if Self::is_container(type_) => {
+                let results_name = Self::name(&format!("{}_results", name));
+                w.eob()?;
+                w.write_line(format!(
+                    "pub const {} = {};",
+                    results_name,
+                    Self::anonymous_type(type_)
+                ))?;
+                results_name
+            }
+            Some(ok) => Self::type_ref(ok),
+        };
+        for param in &signature.params {
+            if let ParamKind::Out(_) = param.kind {
+                match param.name.strip_prefix("result") {
+                    Some("_ptr") => args.push("&result".to_string()),
+                    Some(member) => args.push(format!(
+                        "&result.@\"{}\"",
+                        member.trim_end_matches("_ptr")
+                    )),
+                    None => unreachable!(),
+                }
+            }
+        }
+
+        w.eob()?;
+        Self::write_docs(w, &func.docs)?;
+        w.write_line(format!(
+            "pub fn {}({}) {}!{} {{",
+            Self::wrapper_name(name),
+            params.join(", "),
+            Self::name(&format!("{}_error", error_name)),
+            ok_type
+        ))?;
+        {
+            let mut w = w.new_block();
+            if signature.ok.is_some() {
+                w.write_line(format!("var result: {} = undefined;", ok_type))?;
+            }
+            w.write_line(format!(
+                "try {}({}({}));",
+                Self::name(&format!("{}_check", error_name)),
+                Self::name(name),
+                args.join(", ")
+            ))?;
+            if signature.ok.is_some() {
+                w.write_line("return result;")?;
+            }
+        }
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    /// Name the anonymous struct, union and enum types of parameters, and of
+    /// the elements of list parameters, so that wrappers can pass them along
+    fn define_param_types<T: Write>(
+        w: &mut PrettyWriter<T>,
+        func: &witx::InterfaceFunc,
+    ) -> Result<(), Error> {
+        let func_name = func.name.as_str();
+        for param in &func.params {
+            let (name, tref) = match param.tref.type_().as_ref() {
+                witx::Type::List(element_type) => {
+                    (format!("{}_element", param.name.as_str()), element_type)
+                }
+                _ => (param.name.as_str().to_string(), &param.tref),
+            };
+            if let witx::TypeRef::Value(type_) = tref {
+                if Self::is_container(type_) {
+                    w.write_line(format!(
+                        "pub const {} = {};",
+                        Self::param_type(func_name, &name, tref),
+                        Self::anonymous_type(type_)
+                    ))?
+                    .eob()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The type of a parameter, or of the elements of a list parameter, named
+    /// by `define_param_types()` if it is anonymous
+    fn param_type(func_name: &str, name: &str, tref: &witx::TypeRef) -> String {
+        match tref {
+            witx::TypeRef::Value(type_) if Self::is_container(type_) => {
+                Self::name(&format!("{}_{}", func_name, name))
+            }
+            _ => Self::type_ref(tref),
+        }
+    }
+
+    /// Define an error set with every non-zero case of an enum, and a function
+    /// to convert the enum to an error union
+    fn define_error_set<T: Write>(
+        w: &mut PrettyWriter<T>,
+        error_name: &str,
+        variant: &witx::Variant,
+    ) -> Result<(), Error> {
+        let cases: Vec<_> = variant
+            .cases
+            .iter()
+            .skip(1)
+            .map(|case| Self::name(case.name.as_str()))
+            .collect();
+        w.write_line(format!(
+            "pub const {} = error{{ {}, Unexpected }};",
+            Self::name(&format!("{}_error", error_name)),
+            cases.join(", ")
+        ))?
+        .eob()?;
+        w.write_line(format!(
+            "pub fn {}(e: {}) {}!void {{",
+            Self::name(&format!("{}_check", error_name)),
+            Self::type_name(error_name),
+            Self::name(&format!("{}_error", error_name))
+        ))?;
+        {
+            let mut w = w.new_block();
+            w.write_line("return switch (e) {")?;
+            {
+                let mut w = w.new_block();
+                if let Some(success) = variant.cases.first() {
+                    w.write_line(format!(".{} => {{}},", Self::name(success.name.as_str())))?;
+                }
+                for case in &cases {
+                    w.write_line(format!(".{} => error.{},", case, case))?;
+                }
+                w.write_line("else => error.Unexpected,")?;
+            }
+            w.write_line("};")?;
+        }
+        w.write_line("}")?;
+        Ok(())
+    }
+
+    /// Enums are non-exhaustive, as the host may return values that weren't
+    /// defined yet, and any other value would be illegal behavior
+    fn define_enum_cases<T: Write>(
+        w: &mut PrettyWriter<T>,
+        variant: &witx::Variant,
+    ) -> Result<(), Error> {
+        for case in &variant.cases {
+            Self::write_docs(w, &case.docs)?;
+            w.write_line(format!("{},", Self::name(case.name.as_str())))?;
+        }
+        w.write_line("_,")?;
+        Ok(())
+    }
+
+    fn write_docs<T: Write>(w: &mut PrettyWriter<T>, docs: &str) -> Result<(), Error> {
+        for docs_line in docs.lines() {
+            w.write_line(format!("/// {}", docs_line))?;
+        }
+        Ok(())
+    }
+
+    /// Escape an identifier that is a keyword or doesn't start with a letter
+    fn name(name: &str) -> String {
+        let is_valid_start = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+        if !is_valid_start || KEYWORDS.contains(&name) {
+            format!("@\"{}\"", name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Type names get a `_t` suffix, so that they don't clash with parameters
+    fn type_name(name: &str) -> String {
+        Self::name(&format!("{}_t", name))
+    }
+
+    fn wrapper_name(name: &str) -> String {
+        let mut camel_name = String::with_capacity(name.len());
+        let mut upper = false;
+        for c in name.chars() {
+            if c == '_' {
+                upper = true;
+            } else if upper {
+                camel_name.extend(c.to_uppercase());
+                upper = false;
+            } else {
+                camel_name.push(c);
+            }
+        }
+        if camel_name == name {
+            camel_name.push_str("Checked");
+        }
+        Self::name(&camel_name)
+    }
+
+    fn builtin(builtin: &witx::BuiltinType) -> &'static str {
+        match builtin {
+            witx::BuiltinType::Char => "u32",
+            witx::BuiltinType::U8 { .. } => "u8",
+            witx::BuiltinType::U16 => "u16",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: true,
+            } => "usize",
+            witx::BuiltinType::U32 {
+                lang_ptr_size: false,
+            } => "u32",
+            witx::BuiltinType::U64 => "u64",
+            witx::BuiltinType::S8 => "i8",
+            witx::BuiltinType::S16 => "i16",
+            witx::BuiltinType::S32 => "i32",
+            witx::BuiltinType::S64 => "i64",
+            witx::BuiltinType::F32 => "f32",
+            witx::BuiltinType::F64 => "f64",
+        }
+    }
+
+    /// A slice, for parameters of wrappers
+    fn list(func_name: &str, element_name: &str, element_type: &witx::TypeRef) -> String {
+        if abi::is_string(element_type) {
+            "[]const u8".to_string()
+        } else {
+            format!(
+                "[]const {}",
+                Self::param_type(func_name, element_name, element_type)
+            )
+        }
+    }
+
+    /// A pointer and a length, for lists stored in extern structs and unions,
+    /// which can't contain slices
+    fn list_struct(element_type: &witx::TypeRef) -> String {
+        let element_type = if abi::is_string(element_type) {
+            "u8".to_string()
+        } else {
+            Self::type_ref(element_type)
+        };
+        format!("extern struct {{ ptr: [*]const {}, len: usize }}", element_type)
+    }
+
+    /// Anonymous types that are written as a struct, union or enum literal
+    fn is_container(type_: &witx::Type) -> bool {
+        match type_ {
+            witx::Type::Record(record) => record.bitflags_repr().is_none(),
+            witx::Type::Variant(variant) => !variant.is_bool(),
+            witx::Type::List(_) => true,
+            _ => false,
+        }
+    }
+
+    fn type_ref(tref: &witx::TypeRef) -> String {
+        match tref {
+            witx::TypeRef::Name(named_type) => Self::type_name(named_type.name.as_str()),
+            witx::TypeRef::Value(type_) => Self::anonymous_type(type_),
+        }
+    }
+
+    fn anonymous_type(type_: &witx::Type) -> String {
+        match type_ {
+            witx::Type::Builtin(builtin) => Self::builtin(builtin).to_string(),
+            witx::Type::Pointer(tref) => format!("[*]{}", Self::type_ref(tref)),
+            witx::Type::ConstPointer(tref) => format!("[*]const {}", Self::type_ref(tref)),
+            witx::Type::List(element_type) => Self::list_struct(element_type),
+            witx::Type::Handle(_) => "i32".to_string(),
+            witx::Type::Record(record) => {
+                let members: Vec<_> = record
+                    .members
+                    .iter()
+                    .map(|member| {
+                        format!(
+                            "{}: {}",
+                            Self::name(member.name.as_str()),
+                            Self::type_ref(&member.tref)
+                        )
+                    })
+                    .collect();
+                format!("extern struct {{ {} }}", members.join(", "))
+            }
+            witx::Type::Variant(variant) if variant.is_bool() => Self::BOOL.to_string(),
+            witx::Type::Variant(variant) => {
+                let tag_type = Self::builtin(&variant.tag_repr.to_builtin());
+                let cases: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .map(|case| Self::name(case.name.as_str()))
+                    .collect();
+                let tag = format!("enum({}) {{ {}, _ }}", tag_type, cases.join(", "));
+                if variant.is_enum() {
+                    return tag;
+                }
+                let payloads: Vec<_> = variant
+                    .cases
+                    .iter()
+                    .filter_map(|case| {
+                        case.tref.as_ref().map(|case_type| {
+                            format!(
+                                "{}: {}",
+                                Self::name(case.name.as_str()),
+                                Self::type_ref(case_type)
+                            )
+                        })
+                    })
+                    .collect();
+                format!(
+                    "extern struct {{ tag: {}, payload: extern union {{ {} }} }}",
+                    tag,
+                    payloads.join(", ")
+                )
+            }
+        }
+    }
+}
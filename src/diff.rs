@@ -0,0 +1,230 @@
+/// Lines of context around changes
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A line-level edit, with the position of the line in the old and new texts
+#[derive(Clone, Copy)]
+struct Edit {
+    op: Op,
+    old: usize,
+    new: usize,
+}
+
+/// Compute a unified diff between two texts, or `None` if they are identical
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    let old_lines: Vec<_> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<_> = new.split_inclusive('\n').collect();
+    let edits = edits(&old_lines, &new_lines);
+    let mut diff = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for hunk in hunks(&edits) {
+        let old_count = hunk.iter().filter(|edit| edit.op != Op::Insert).count();
+        let new_count = hunk.iter().filter(|edit| edit.op != Op::Delete).count();
+        diff += &format!(
+            "@@ -{} +{} @@\n",
+            range(hunk[0].old, old_count),
+            range(hunk[0].new, new_count)
+        );
+        for edit in hunk {
+            let (prefix, line) = match edit.op {
+                Op::Equal => (' ', old_lines[edit.old]),
+                Op::Delete => ('-', old_lines[edit.old]),
+                Op::Insert => ('+', new_lines[edit.new]),
+            };
+            diff.push(prefix);
+            diff += line;
+            if !line.ends_with('\n') {
+                diff += "\n\\ No newline at end of file\n";
+            }
+        }
+    }
+    Some(diff)
+}
+
+/// Format the range of lines of a hunk starting at a 0-based index, as `line,count`
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Group edits into hunks of changes, surrounded by a few lines of context
+fn hunks(edits: &[Edit]) -> Vec<&[Edit]> {
+    let changes: Vec<_> = (0..edits.len())
+        .filter(|&i| edits[i].op != Op::Equal)
+        .collect();
+    let mut hunks = vec![];
+    let mut i = 0;
+    while i < changes.len() {
+        let start = changes[i].saturating_sub(CONTEXT);
+        let mut end = changes[i];
+        while i < changes.len() && changes[i] <= end + 2 * CONTEXT + 1 {
+            end = changes[i];
+            i += 1;
+        }
+        hunks.push(&edits[start..(end + CONTEXT + 1).min(edits.len())]);
+    }
+    hunks
+}
+
+/// Shortest edit script between two lists of lines, using Myers' algorithm
+fn edits(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(a.len().max(b.len()));
+    diff_range(a, b, 0, 0, &mut edits);
+    edits
+}
+
+/// Append the edits turning `a` into `b`, which start at `a_start` and `b_start`
+/// in the full texts.
+///
+/// This is the linear space variant of the algorithm: the middle of the shortest
+/// edit script is found first, and both sides of it are then diffed recursively.
+fn diff_range(a: &[&str], b: &[&str], a_start: usize, b_start: usize, edits: &mut Vec<Edit>) {
+    // Common prefixes and suffixes are usually most of the file
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    edits.extend((0..prefix).map(|i| Edit {
+        op: Op::Equal,
+        old: a_start + i,
+        new: b_start + i,
+    }));
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (a_mid_start, b_mid_start) = (a_start + prefix, b_start + prefix);
+
+    if a_mid.is_empty() || b_mid.is_empty() {
+        replace(a_mid, b_mid, a_mid_start, b_mid_start, edits);
+    } else if let Some((x, y)) = split_point(a_mid, b_mid) {
+        diff_range(&a_mid[..x], &b_mid[..y], a_mid_start, b_mid_start, edits);
+        diff_range(
+            &a_mid[x..],
+            &b_mid[y..],
+            a_mid_start + x,
+            b_mid_start + y,
+            edits,
+        );
+    } else {
+        // Too different for a minimal diff to be worth the time it takes
+        replace(a_mid, b_mid, a_mid_start, b_mid_start, edits);
+    }
+
+    edits.extend((0..suffix).map(|i| Edit {
+        op: Op::Equal,
+        old: a_start + a.len() - suffix + i,
+        new: b_start + b.len() - suffix + i,
+    }));
+}
+
+/// Append edits deleting all of `a`, then inserting all of `b`
+fn replace(a: &[&str], b: &[&str], a_start: usize, b_start: usize, edits: &mut Vec<Edit>) {
+    edits.extend((0..a.len()).map(|i| Edit {
+        op: Op::Delete,
+        old: a_start + i,
+        new: b_start,
+    }));
+    edits.extend((0..b.len()).map(|i| Edit {
+        op: Op::Insert,
+        old: a_start + a.len(),
+        new: b_start + i,
+    }));
+}
+
+/// Maximum number of edits searched from each end, before giving up on a minimal diff
+const MAX_EDITS: usize = 1024;
+
+/// Find a point in the middle of the shortest edit script, by searching from
+/// both ends at the same time until the paths overlap.
+///
+/// Returns `None` if the texts need more than `2 * MAX_EDITS` edits.
+/// `a` and `b` must not be empty, nor start or end with the same line.
+fn split_point(a: &[&str], b: &[&str]) -> Option<(usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max_d = ((n + m + 1) / 2).min(MAX_EDITS as isize);
+    // The furthest `x` reached on each diagonal `k = x - y`, forwards from the
+    // start, and backwards from the end in reversed coordinates, or -1
+    let offset = max_d;
+    let len = 2 * max_d + 2;
+    let mut forward = vec![-1isize; len as usize];
+    let mut backward = vec![-1isize; len as usize];
+    forward[(offset + 1) as usize] = 0;
+    backward[(offset + 1) as usize] = 0;
+    let delta = n - m;
+    // With an odd difference, the paths meet while extending the forward one
+    let front = delta % 2 != 0;
+    // Diagonals that went past the end of a text are skipped
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+    for d in 0..max_d {
+        for k1 in (-d + k1_start..=d - k1_end).step_by(2) {
+            let k1_offset = (offset + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && forward[k1_offset - 1] < forward[k1_offset + 1])
+            {
+                forward[k1_offset + 1]
+            } else {
+                forward[k1_offset - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && a[x1 as usize] == b[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[k1_offset] = x1;
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let k2_offset = offset + delta - k1;
+                if k2_offset >= 0 && k2_offset < len && backward[k2_offset as usize] != -1 {
+                    let x2 = n - backward[k2_offset as usize];
+                    if x1 >= x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+        }
+        for k2 in (-d + k2_start..=d - k2_end).step_by(2) {
+            let k2_offset = (offset + k2) as usize;
+            let mut x2 =
+                if k2 == -d || (k2 != d && backward[k2_offset - 1] < backward[k2_offset + 1]) {
+                    backward[k2_offset + 1]
+                } else {
+                    backward[k2_offset - 1] + 1
+                };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && a[(n - 1 - x2) as usize] == b[(m - 1 - y2) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            backward[k2_offset] = x2;
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let k1_offset = offset + delta - k2;
+                if k1_offset >= 0 && k1_offset < len && forward[k1_offset as usize] != -1 {
+                    let x1 = forward[k1_offset as usize];
+                    let y1 = offset + x1 - k1_offset;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
@@ -1,10 +1,47 @@
 use std::fmt;
-use witx::WitxError;
+use std::path::PathBuf;
+use witx::{Location, ValidationError, WitxError, WitxIo};
+
+/// Exit code when generated code is not up to date
+pub const EXIT_CHECK_FAILED: i32 = 1;
+
+/// Exit code for invalid command-line options (`EX_USAGE`)
+pub const EXIT_USAGE_ERROR: i32 = 64;
+
+/// Exit code for invalid witx definitions (`EX_DATAERR`)
+pub const EXIT_DATA_ERROR: i32 = 65;
+
+/// Exit code for errors reading or writing files (`EX_IOERR`)
+pub const EXIT_IO_ERROR: i32 = 74;
 
 #[derive(Debug)]
 pub enum Error {
     Witx(WitxError),
     Io(std::io::Error),
+    File(PathBuf, std::io::Error),
+    Usage(String),
+    /// A file doesn't contain the code that would be generated
+    OutOfDate(PathBuf),
+}
+
+impl Error {
+    /// The process exit code to use when reporting this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Witx(WitxError::Io(..)) | Error::Io(_) | Error::File(..) => EXIT_IO_ERROR,
+            Error::Witx(_) => EXIT_DATA_ERROR,
+            Error::Usage(_) => EXIT_USAGE_ERROR,
+            Error::OutOfDate(_) => EXIT_CHECK_FAILED,
+        }
+    }
+
+    /// Same as `to_string()`, reading the source of invalid definitions through `witxio`
+    pub fn report_with(&self, witxio: &dyn WitxIo) -> String {
+        match self {
+            Error::Witx(WitxError::Validation(e)) => validation_report(e, witxio),
+            _ => self.to_string(),
+        }
+    }
 }
 
 impl From<std::io::Error> for Error {
@@ -15,13 +52,28 @@ impl From<std::io::Error> for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", &self)
+        match self {
+            Error::Witx(WitxError::Io(path, e)) | Error::File(path, e) => {
+                write!(f, "{}: {}", path.display(), e)
+            }
+            Error::Witx(WitxError::Validation(e)) => {
+                write!(f, "{}", validation_report(e, &witx::Filesystem))
+            }
+            Error::Witx(e) => write!(f, "{}", e.report().trim_end()),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Usage(e) => write!(f, "{}", e),
+            Error::OutOfDate(path) => write!(f, "{}: not up to date", path.display()),
+        }
     }
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        match self {
+            Error::Witx(e) => Some(e),
+            Error::Io(e) | Error::File(_, e) => Some(e),
+            Error::Usage(_) | Error::OutOfDate(_) => None,
+        }
     }
 }
 
@@ -30,3 +82,49 @@ impl From<WitxError> for Error {
         Self::Witx(e)
     }
 }
+
+/// Format a validation error with the location and source of the offending definition
+fn validation_report(e: &ValidationError, witxio: &dyn WitxIo) -> String {
+    use ValidationError::*;
+    match e {
+        UnknownName { location, .. }
+        | WrongKindName { location, .. }
+        | Recursive { location, .. }
+        | InvalidRepr { location, .. }
+        | Abi { location, .. }
+        | AnonymousRecord { location, .. }
+        | UnionSizeMismatch { location, .. }
+        | InvalidUnionField { location, .. }
+        | InvalidUnionTag { location, .. } => format!("{}\n{}", e, code_frame(location, witxio)),
+        NameAlreadyExists {
+            at_location,
+            previous_location,
+            ..
+        } => format!(
+            "{}\n{}\nnote: originally defined here\n{}",
+            e,
+            code_frame(at_location, witxio),
+            code_frame(previous_location, witxio)
+        ),
+    }
+}
+
+/// Show a location, followed by the source line with the column highlighted
+fn code_frame(location: &Location, witxio: &dyn WitxIo) -> String {
+    let mut frame = format!(
+        "     --> {}:{}:{}",
+        location.path.display(),
+        location.line,
+        location.column
+    );
+    if let Ok(src_line) = witxio.fget_line(&location.path, location.line) {
+        frame += &format!(
+            "\n      |\n{:>5} | {}\n      | {:>column$}",
+            location.line,
+            src_line,
+            "^",
+            column = location.column
+        );
+    }
+    frame
+}
@@ -1,395 +1,229 @@
-use witx::Layout;
-
-use crate::astype::*;
+use crate::backend::Backend;
 use crate::error::*;
+use crate::glob;
 use crate::pretty_writer::PrettyWriter;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 use std::io::Write;
 use std::path::Path;
 
-pub struct Generator<W: Write> {
-    w: PrettyWriter<W>,
-    module_name: Option<String>,
+/// Which modules and functions to generate, and the names they are imported from
+#[derive(Clone, Debug, Default)]
+pub struct GeneratorOptions {
+    /// Import module name to use for all modules
+    pub module_name: Option<String>,
+    /// Import module names to use for specific modules, by witx module name
+    pub module_map: HashMap<String, String>,
+    /// If not empty, only generate these modules
+    pub only_modules: Vec<String>,
+    /// Modules not to generate
+    pub exclude_modules: Vec<String>,
+    /// If not empty, only generate functions matching these patterns
+    pub include_funcs: Vec<String>,
+    /// Don't generate functions matching these patterns
+    pub exclude_funcs: Vec<String>,
 }
 
-impl<W: Write> Generator<W> {
-    pub fn new(writer: W, module_name: Option<String>) -> Self {
-        let w = PrettyWriter::new(writer, "    ");
-        Generator { w, module_name }
-    }
-
-    pub fn generate<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
-        let document = witx::load(&[path])?;
-        self.header()?;
-        for type_ in document.typenames() {
-            self.define_type(type_.as_ref())?;
-        }
-        for module in document.modules() {
-            self.define_module(module.as_ref())?;
-        }
-        Ok(())
-    }
-
-    fn header(&mut self) -> Result<(), Error> {
-        let w0 = &mut self.w;
-        w0.write_lines(
-            "
-/*
- * This file was automatically generated by as-witx - Do not edit manually.
- */",
-        )?;
-        w0.write_lines(
-            "
-export type handle = i32;
-export type char = u8;
-export type ptr<T> = usize;
-export type mut_ptr<T> = usize;
-export type untyped_ptr = usize;
-export type struct<T> = usize;
-export type union<T> = usize;
-export type wasi_string_ptr = ptr<char>;
-",
-        )?;
-        w0.write_lines(
-            "
-@unmanaged
-export class WasiString {
-    ptr: wasi_string_ptr;
-    length: usize;
-
-    constructor(str: string) {
-        let wasiString = String.UTF8.encode(str, false);
-        // @ts-ignore: cast
-        this.ptr = changetype<wasi_string_ptr>(wasiString);
-        this.length = wasiString.byteLength;
-    }
-
-    toString(): string {
-        let tmp = new ArrayBuffer(this.length as u32);
-        memory.copy(changetype<usize>(tmp), this.ptr, this.length);
-        return String.UTF8.decode(tmp);
+impl GeneratorOptions {
+    fn has_filters(&self) -> bool {
+        !(self.only_modules.is_empty()
+            && self.exclude_modules.is_empty()
+            && self.include_funcs.is_empty()
+            && self.exclude_funcs.is_empty())
     }
 }
 
-@unmanaged
-export class WasiArray<T> {
-    ptr: ptr<T>;
-    length: usize;
+/// Name of the file containing the types, when the output is split
+const TYPES_FILE: &str = "types";
 
-    constructor(array: ArrayBufferView) {
-        // @ts-ignore: cast
-        this.ptr = array.dataStart;
-        this.length = array.byteLength;
-    }
+/// Name of the file re-exporting all the other files, when the output is split
+const INDEX_FILE: &str = "index";
+
+pub struct Generator<W: Write> {
+    w: PrettyWriter<W>,
+    backend: Box<dyn Backend<W>>,
+    options: GeneratorOptions,
 }
-",
-        )?
-        .eob()?;
-        Ok(())
-    }
 
-    fn define_as_alias<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        other_type: &ASType,
-    ) -> Result<(), Error> {
-        w.write_line(format!("export type {} = {};", as_type, other_type))?;
-        Ok(())
+impl<W: Write> Generator<W> {
+    pub fn new(writer: W, backend: Box<dyn Backend<W>>, options: GeneratorOptions) -> Self {
+        let w = PrettyWriter::new(writer, "    ");
+        Generator {
+            w,
+            backend,
+            options,
+        }
     }
 
-    fn define_as_handle<T: Write>(w: &mut PrettyWriter<T>, as_type: &ASType) -> Result<(), Error> {
-        w.write_line(format!("export type {} = {};", as_type, ASType::Handle))?;
-        Ok(())
+    /// Generate code from witx files
+    pub fn generate<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<(), Error> {
+        let document = witx::load(paths)?;
+        self.generate_document(&document)
     }
 
-    fn define_variant_case_accessors<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        i: usize,
-        variant: &witx::Case,
-    ) -> Result<(), Error> {
-        let variant_name = variant.name.as_str();
-        match variant.tref.as_ref() {
-            None => {
-                w.write_line(format!("static {}(): {} {{", variant_name, as_type))?
-                    .indent()?
-                    .write_line(format!("return {}.new({});", as_type, i))?
-                    .write_line("}")?
-                    .eob()?;
-
-                w.write_line(format!("set_{}(): void {{", variant_name))?
-                    .indent()?
-                    .write_line(format!("this.tag = {};", i))?
-                    .write_line("}")?
-                    .eob()?;
-
-                w.write_line(format!("is_{}(): bool {{", variant_name))?
-                    .indent()?
-                    .write_line(format!("return this.tag === {};", i))?
-                    .write_line("}")?;
-            }
-            Some(variant_type) => {
-                let as_variant_type = ASType::from(variant_type);
-                w.write_line(format!(
-                    "static {}(val: {}): {} {{",
-                    variant_name, as_variant_type, as_type
-                ))?;
-                w.new_block()
-                    .write_line(format!("return {}.new({}, val);", as_type, i))?;
-                w.write_line("}")?.eob()?;
-
-                w.write_line(format!(
-                    "set_{}(val: {}): void {{",
-                    variant_name, as_variant_type
-                ))?;
-                {
-                    w.new_block()
-                        .write_line(format!("this.tag = {};", i))?
-                        .write_line("this.set(val);")?;
-                }
-                w.write_line("}")?.eob()?;
-
-                w.write_line(format!("is_{}(): bool {{", variant_name))?
-                    .indent()?
-                    .write_line(format!("return this.tag === {};", i))?
-                    .write_line("}")?
-                    .eob()?;
-
-                if as_variant_type.is_nullable() {
-                    w.write_line(format!(
-                        "get_{}(): {} | null {{",
-                        variant_name, as_variant_type
-                    ))?;
-                } else {
-                    w.write_line(format!("get_{}(): {} {{", variant_name, as_variant_type))?;
-                }
-                {
-                    let mut w = w.new_block();
-                    if as_variant_type.is_nullable() {
-                        w.write_line(format!("if (this.tag !== {}) {{ return null; }}", i))?;
-                    }
-                    w.write_line(format!("return this.get<{}>();", as_variant_type))?;
-                }
-                w.write_line("}")?;
-            }
+    /// Generate code from a witx document that has already been loaded
+    pub fn generate_document(&mut self, document: &witx::Document) -> Result<(), Error> {
+        let (modules, used_types) = self.select(document)?;
+        self.backend.header(&mut self.w)?;
+        self.define_types(document, used_types.as_ref())?;
+        for (module, funcs) in &modules {
+            self.define_module(module, funcs)?;
         }
+        self.backend.footer(&mut self.w)?;
         Ok(())
     }
 
-    fn define_variant_case<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        i: usize,
-        variant: &witx::Case,
-    ) -> Result<(), Error> {
-        let variant_name = variant.name.as_str();
-        match variant.tref.as_ref() {
-            None => {
-                w.write_line(format!("// --- {}: void if tag={}", variant_name, i))?;
-            }
-            Some(variant_type) => {
-                w.write_line(format!(
-                    "// --- {}: {} if tag={}",
-                    variant_name,
-                    ASType::from(variant_type),
-                    i
-                ))?;
-            }
-        }
-        w.eob()?;
-        Self::define_variant_case_accessors(w, as_type, i, variant)?;
-        Ok(())
+    /// Generate the types, each module and an index re-exporting them into separate files.
+    ///
+    /// `create` returns the output for a file name, given without an extension.
+    pub fn generate_split<P, F>(&mut self, paths: &[P], create: F) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&str) -> Result<W, Error>,
+    {
+        let document = witx::load(paths)?;
+        self.generate_document_split(&document, create)
     }
 
-    fn define_as_variant<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        union: &witx::Variant,
-    ) -> Result<(), Error> {
-        let as_tag = ASType::from(&union.tag_repr);
-        let variants = &union.cases;
-
-        let val_offset = union.payload_offset();
-        let val_size = union.mem_size();
-        w.write_line("// @ts-ignore: decorator")?
-         .write_line("@unmanaged")?
-         .write_line(format!("export class {} {{", as_type))?;
-        {
-            let mut w = w.new_block();
-            w.write_line(format!("tag: {};", as_tag))?;
-            let pad_len = val_offset + val_size;
-            for i in 0..pad_len / 8 {
-                w.write_line(format!("private __pad64_{}: u64;", i))?;
-            }
-            for i in 0..(pad_len & 7) / 4 {
-                w.write_line(format!("private __pad32_{}: u32;", i))?;
-            }
-            for i in 0..(pad_len & 3) / 2 {
-                w.write_line(format!("private __pad16_{}: u16;", i))?;
-            }
-            for i in 0..(pad_len & 1) {
-                w.write_line(format!("private __pad8_{}: u8;", i))?;
-            }
-            w.eob()?;
-
-            w.write_line(format!("constructor(tag: {}) {{", as_tag))?;
-            {
-                let mut w = w.new_block();
-                w.write_line("this.tag = tag;")?.write_line(format!(
-                    "memory.fill(changetype<usize>(this) + {}, 0, {});",
-                    val_offset, val_size
-                ))?;
-            }
-            w.write_line("}")?.eob()?;
-
-            w.write_line("// @ts-ignore: default")?.write_line(format!(
-                "static new<T>(tag: u8, val: T = 0): {} {{",
-                as_type
-            ))?;
-            {
-                let mut w = w.new_block();
-                w.write_line(format!("let tu = new {}(tag);", as_type))?
-                 .write_line("tu.set(val);")?
-                 .write_line("return tu;")?;
-            }
-            w.write_line("}")?.eob()?;
-
-            w.write_line("get<T>(): T {")?;
-            {
-                let mut w = w.new_block();
-                w.write_line("// @ts-ignore: cast")?
-                    .write_line(format!(
-                        "let valBuf = changetype<usize>(this) + {};",
-                        val_offset
-                    ))?
-                    .write_line("if (isReference<T>()) {")?;
-                w.new_block().write_line("return changetype<T>(valBuf);")?;
-                w.write_line("} else {")?;
-                w.new_block().write_line("return load<T>(valBuf);")?;
-                w.write_line("}")?;
-            }
-            w.write_line("}")?.eob()?;
-
-            w.write_line("// @ts-ignore: default")?
-                .write_line("set<T>(val: T = 0): void {")?;
-            {
-                let mut w = w.new_block();
-                w.write_line("// @ts-ignore: cast")?
-                    .write_line(format!(
-                        "let valBuf = changetype<usize>(this) + {};",
-                        val_offset
-                    ))?
-                    .write_line(format!("memory.fill(valBuf, 0, {});", val_size))?
-                    .write_line("if (isReference<T>()) {")?;
-                w.new_block().write_line(
-                    "(val !== null) && memory.copy(valBuf, changetype<usize>(val), offsetof<T>());",
-                )?;
-                w.write_line("} else {")?;
-                w.new_block().write_line("store<T>(valBuf, val)")?;
-                w.write_line("}")?;
-            }
-            w.write_line("}")?;
-
-            for (i, variant) in variants.iter().enumerate() {
-                w.eob()?;
-                Self::define_variant_case(&mut w, as_type, i, variant)?;
-            }
+    /// Same as `generate_split()`, from a witx document that has already been loaded
+    pub fn generate_document_split<F>(
+        &mut self,
+        document: &witx::Document,
+        mut create: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&str) -> Result<W, Error>,
+    {
+        let (modules, used_types) = self.select(document)?;
+        for (module, _) in &modules {
+            Self::check_file_name(module.name.as_str())?;
+        }
+        self.w = PrettyWriter::new(create(TYPES_FILE)?, "    ");
+        self.backend.header(&mut self.w)?;
+        self.define_types(document, used_types.as_ref())?;
+        self.backend.footer(&mut self.w)?;
+
+        let mut files = vec![TYPES_FILE.to_string()];
+        for (module, funcs) in &modules {
+            let file = module.name.as_str().to_string();
+            self.w = PrettyWriter::new(create(&file)?, "    ");
+            let mut type_names: Vec<_> = Self::used_types(funcs.iter()).into_iter().collect();
+            type_names.sort();
+            self.backend
+                .define_imports(&mut self.w, TYPES_FILE, &type_names)?;
+            self.define_module(module, funcs)?;
+            self.backend.footer(&mut self.w)?;
+            files.push(file);
         }
-        w.write_line("}")?;
-
-        Ok(())
-    }
 
-    fn define_as_builtin<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        actual_as_type: &ASType,
-    ) -> Result<(), Error> {
-        w.write_line(format!("export type {} = {};", as_type, actual_as_type))?;
+        self.w = PrettyWriter::new(create(INDEX_FILE)?, "    ");
+        self.backend.define_index(&mut self.w, &files)?;
         Ok(())
     }
 
-    fn define_as_record<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        record: &witx::RecordDatatype,
-    ) -> Result<(), Error> {
-        let variants = &record.members;
-        w.write_line("// @ts-ignore: decorator")?
-            .write_line("@unmanaged")?
-            .write_line(format!("class {} {{", as_type))?;
-        {
-            let mut w = w.new_block();
-            for variant in variants {
-                let variant_name = variant.name.as_str();
-                let variant_type = ASType::from(&variant.tref);
-                Self::write_docs(&mut w, &variant.docs)?;
-                w.write_line(format!("{}: {};", variant_name, variant_type))?;
-            }
-        }
-        w.write_line("}")?;
-        Ok(())
+    /// Return the output, once the code has been generated
+    pub fn into_inner(self) -> W {
+        self.w
+            .into_inner()
+            .expect("The output is still used by another writer")
     }
 
-    fn define_as_list<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        actual_as_type: &ASType,
-    ) -> Result<(), Error> {
-        w.write_line(format!(
-            "export type {} = WasiArray<{}>;",
-            as_type, actual_as_type
-        ))?;
-        Ok(())
+    /// The modules to generate with their functions, and the types to generate if not all of them
+    #[allow(clippy::type_complexity)]
+    fn select(
+        &self,
+        document: &witx::Document,
+    ) -> Result<
+        (
+            Vec<(Rc<witx::Module>, Vec<Rc<witx::InterfaceFunc>>)>,
+            Option<HashSet<String>>,
+        ),
+        Error,
+    > {
+        self.check_module_names(document)?;
+        self.check_func_patterns(document)?;
+        let modules: Vec<_> = document
+            .modules()
+            .filter(|module| self.is_module_included(module.name.as_str()))
+            .map(|module| {
+                let funcs = self.included_funcs(&module);
+                (module, funcs)
+            })
+            .filter(|(_, funcs)| !funcs.is_empty() || self.options.include_funcs.is_empty())
+            .collect();
+        // Without filters, all types are generated, even if no function uses them
+        let used_types = if self.options.has_filters() {
+            let funcs = modules.iter().flat_map(|(_, funcs)| funcs.iter());
+            Some(Self::used_types(funcs))
+        } else {
+            None
+        };
+        Ok((modules, used_types))
     }
 
-    fn define_as_witx_type<T: Write>(
-        w: &mut PrettyWriter<T>,
-        as_type: &ASType,
-        witx_type: &witx::Type,
+    fn define_types(
+        &mut self,
+        document: &witx::Document,
+        used_types: Option<&HashSet<String>>,
     ) -> Result<(), Error> {
-        use witx::Type::*;
-        match witx_type {
-            Handle(_handle) => Self::define_as_handle(w, as_type)?,
-            Builtin(builtin) => Self::define_as_builtin(w, as_type, &builtin.into())?,
-            Variant(ref variant) => Self::define_as_variant(w, as_type, variant)?,
-            Record(ref record) =>  Self::define_as_record(w, as_type, record)?,
-            List(elem) => Self::define_as_list(w, as_type, &ASType::from(elem))?,
-            ConstPointer(_) | witx::Type::Pointer(_) => {
-                panic!("Typedef's pointers are not implemented")
+        let constants: Vec<_> = document.constants().collect();
+        // witx rejects references to types that aren't defined yet, so types
+        // are always listed after the types they depend on
+        for type_ in document.typenames() {
+            if let Some(used_types) = used_types {
+                if !used_types.contains(type_.name.as_str()) {
+                    continue;
+                }
             }
-        };
+            self.define_type(type_.as_ref(), &constants)?;
+        }
         Ok(())
     }
 
-    fn define_type(&mut self, type_: &witx::NamedType) -> Result<(), Error> {
+    fn define_type(
+        &mut self,
+        type_: &witx::NamedType,
+        constants: &[&witx::Constant],
+    ) -> Result<(), Error> {
         let w0 = &mut self.w;
-        let as_type = ASType::Alias(type_.name.as_str().to_string());
-        let docs = &type_.docs;
-        if docs.is_empty() {
-            w0.write_line(format!("/** {} */", as_type))?;
-        } else {
-            Self::write_docs(w0, &type_.docs)?;
-        }
-        let tref = &type_.tref;
-        match tref {
-            witx::TypeRef::Name(other_type) => {
-                Self::define_as_alias(w0, &as_type, &other_type.as_ref().into())?
-            }
+        let backend = &mut self.backend;
+        backend.define_type_docs(w0, type_)?;
+        match &type_.tref {
+            witx::TypeRef::Name(other_type) => backend.define_alias(w0, type_, other_type)?,
             witx::TypeRef::Value(witx_type) => {
-                Self::define_as_witx_type(w0, &as_type, &witx_type.as_ref())?
+                use witx::Type::*;
+                match witx_type.as_ref() {
+                    Handle(handle) => backend.define_handle(w0, type_, handle)?,
+                    Builtin(builtin) => backend.define_builtin(w0, type_, builtin)?,
+                    Variant(variant) => backend.define_variant(w0, type_, variant)?,
+                    Record(record) => backend.define_record(w0, type_, record)?,
+                    List(elem) => backend.define_list(w0, type_, elem)?,
+                    ConstPointer(pointee) => backend.define_pointer(w0, type_, pointee, true)?,
+                    Pointer(pointee) => backend.define_pointer(w0, type_, pointee, false)?,
+                }
             }
         };
+        let constants: Vec<_> = constants
+            .iter()
+            .copied()
+            .filter(|constant| constant.ty == type_.name)
+            .collect();
+        if !constants.is_empty() {
+            w0.eob()?;
+            backend.define_constants(w0, type_, &constants)?;
+        }
         w0.eob()?;
         Ok(())
     }
 
-    fn define_module(&mut self, module: &witx::Module) -> Result<(), Error> {
+    fn define_module(
+        &mut self,
+        module: &witx::Module,
+        funcs: &[Rc<witx::InterfaceFunc>],
+    ) -> Result<(), Error> {
         let w = &mut self.w.clone();
-        w.eob()?.write_line(format!(
-            "// ----------------------[{}]----------------------",
-            module.name.as_str()
-        ))?;
-        for func in module.funcs() {
+        self.backend.define_module(w, module)?;
+        for func in funcs {
             self.define_func(module.name.as_str(), func.as_ref())?;
             w.eob()?;
         }
@@ -397,121 +231,127 @@ export class WasiArray<T> {
     }
 
     fn define_func(&mut self, module_name: &str, func: &witx::InterfaceFunc) -> Result<(), Error> {
-        let module_name = match self.module_name.as_ref() {
-            None => module_name,
+        let options = &self.options;
+        let module_name = match options.module_map.get(module_name) {
             Some(module_name) => module_name.as_str(),
+            None => options.module_name.as_deref().unwrap_or(module_name),
         };
-        let w0 = &mut self.w;
-        let docs = &func.docs;
-        let name = func.name.as_str();
-        if docs.is_empty() {
-            w0.write_line(format!("\n/** {} */", name))?;
-        } else {
-            Self::write_docs(w0, docs)?;
-        }
-        let s_in: Vec<_> = func
-            .params
-            .iter()
-            .map(|param| param.name.as_str().to_string())
-            .collect();
-        let s_out: Vec<_> = func
-            .results
-            .iter()
-            .map(|param| param.name.as_str().to_string())
-            .collect();
-        w0.write_line("/**")?
-            .write_line(format!(" * in:  {}", s_in.join(", ")))?
-            .write_line(format!(" * out: {}", s_out.join(", ")))?
-            .write_line(" */")?;
-        w0.write_line("// @ts-ignore: decorator")?
-            .write_line(format!("@external(\"{}\", \"{}\")", module_name, name))?
-            .write_line(format!("export declare function {}(", name))?;
+        self.backend.define_func(&mut self.w, module_name, func)
+    }
 
-        let params = &func.params;
-        let as_params = Self::params_to_as(params);
-        let results = &func.results;
-        let as_results = Self::params_to_as(results);
-        let return_value = as_results.get(0);
-        let as_results = if as_results.is_empty() {
-            &[]
-        } else {
-            &as_results[1..]
-        };
-        let as_params: Vec<_> = as_params
-            .iter()
-            .map(|(v, t)| format!("{}: {}", v, t))
-            .collect();
-        let as_results: Vec<_> = as_results
-            .iter()
-            .map(|(v, t)| format!("{}_ptr: {}", v, ASType::MutPtr(Box::new(t.clone()))))
-            .collect();
-        if !as_params.is_empty() {
-            if !as_results.is_empty() {
-                w0.continuation()?
-                    .write(as_params.join(", "))?
-                    .write(",")?
-                    .eol()?;
-            } else {
-                w0.continuation()?.write_line(as_params.join(", "))?;
+    fn is_module_included(&self, module_name: &str) -> bool {
+        let options = &self.options;
+        (options.only_modules.is_empty() || options.only_modules.iter().any(|x| x == module_name))
+            && !options.exclude_modules.iter().any(|x| x == module_name)
+    }
+
+    fn is_func_included(&self, func_name: &str) -> bool {
+        let options = &self.options;
+        (options.include_funcs.is_empty()
+            || options
+                .include_funcs
+                .iter()
+                .any(|pattern| glob::is_match(pattern, func_name)))
+            && !options
+                .exclude_funcs
+                .iter()
+                .any(|pattern| glob::is_match(pattern, func_name))
+    }
+
+    fn included_funcs(&self, module: &witx::Module) -> Vec<Rc<witx::InterfaceFunc>> {
+        module
+            .funcs()
+            .filter(|func| self.is_func_included(func.name.as_str()))
+            .collect()
+    }
+
+    /// Names of the types used by a set of functions, directly or not
+    fn used_types<'a>(funcs: impl Iterator<Item = &'a Rc<witx::InterfaceFunc>>) -> HashSet<String> {
+        fn visit(tref: &witx::TypeRef, used_types: &mut HashSet<String>) {
+            let type_ = match tref {
+                witx::TypeRef::Name(named_type) => {
+                    if used_types.insert(named_type.name.as_str().to_string()) {
+                        visit(&named_type.tref, used_types);
+                    }
+                    return;
+                }
+                witx::TypeRef::Value(type_) => type_,
+            };
+            match type_.as_ref() {
+                witx::Type::Record(record) => {
+                    for member in &record.members {
+                        visit(&member.tref, used_types);
+                    }
+                }
+                witx::Type::Variant(variant) => {
+                    for case_type in variant.cases.iter().filter_map(|case| case.tref.as_ref()) {
+                        visit(case_type, used_types);
+                    }
+                }
+                witx::Type::List(tref)
+                | witx::Type::Pointer(tref)
+                | witx::Type::ConstPointer(tref) => visit(tref, used_types),
+                witx::Type::Handle(_) | witx::Type::Builtin(_) => {}
             }
         }
-        let return_as_type_and_comment = match return_value {
-            None => (ASType::Void, "".to_string()),
-            Some(x) => (x.1.clone(), format!(" /* {} */", x.0)),
-        };
-        if !as_results.is_empty() {
-            w0.continuation()?.write_line(as_results.join(", "))?;
+
+        let mut used_types = HashSet::new();
+        for func in funcs {
+            for param in func.params.iter().chain(&func.results) {
+                visit(&param.tref, &mut used_types);
+            }
         }
-        w0.write_line(format!(
-            "): {}{};",
-            return_as_type_and_comment.0, return_as_type_and_comment.1
-        ))?;
-        Ok(())
+        used_types
     }
 
-    fn write_docs<T: Write>(w: &mut PrettyWriter<T>, docs: &str) -> Result<(), Error> {
-        if docs.is_empty() {
-            return Ok(());
-        }
-        w.write_line("/**")?;
-        for docs_line in docs.lines() {
-            w.write_line(format!(" * {}", docs_line))?;
+    /// Check that every function pattern to include matches at least one function
+    fn check_func_patterns(&self, document: &witx::Document) -> Result<(), Error> {
+        for pattern in &self.options.include_funcs {
+            let matches = document
+                .modules()
+                .flat_map(|module| module.funcs().collect::<Vec<_>>())
+                .any(|func| glob::is_match(pattern, func.name.as_str()));
+            if !matches {
+                return Err(Error::Usage(format!("No functions match [{}]", pattern)));
+            }
         }
-        w.write_line(" */")?;
         Ok(())
     }
 
-    fn params_to_as(params: &[witx::InterfaceFuncParam]) -> Vec<(String, ASType)> {
-        let mut as_params = vec![];
-        for param in params {
-            let leaf_type = Self::leaf_type(&param.tref);
-            let as_leaf_type = ASType::from(leaf_type).name(param.tref.type_name());
-            let (first, second) = as_leaf_type.decompose();
-            match &param.tref {
-                witx::TypeRef::Name(name) => {
-                    as_params.push((
-                        format!("{}{}", param.name.as_str(), first.1),
-                        ASType::from(name.as_ref()),
-                    ));
-                }
-                _ => {
-                    as_params.push((format!("{}{}", param.name.as_str(), first.1), first.0));
-                }
-            }
-            if let Some(second) = second {
-                as_params.push((format!("{}{}", param.name.as_str(), second.1), second.0))
+    /// Check that a module file would be a plain file of the output directory,
+    /// that doesn't overwrite the types or the index, even on a case-insensitive
+    /// file system
+    fn check_file_name(module_name: &str) -> Result<(), Error> {
+        if module_name.starts_with('.') || module_name.contains(['/', '\\']) {
+            return Err(Error::Usage(format!(
+                "Module [{}] can't be written to its own file, as its name isn't a file name",
+                module_name
+            )));
+        }
+        for file in &[TYPES_FILE, INDEX_FILE] {
+            if module_name.eq_ignore_ascii_case(file) {
+                return Err(Error::Usage(format!(
+                    "Module [{}] can't be written to its own file, as [{}] is reserved",
+                    module_name, file
+                )));
             }
         }
-        as_params
+        Ok(())
     }
 
-    fn leaf_type(type_ref: &witx::TypeRef) -> &witx::Type {
-        match type_ref {
-            witx::TypeRef::Name(other_type) => {
-                let x = other_type.as_ref();
-                Self::leaf_type(&x.tref)
+    /// Check that the modules the options refer to exist in the document
+    fn check_module_names(&self, document: &witx::Document) -> Result<(), Error> {
+        let options = &self.options;
+        let module_names = options
+            .module_map
+            .keys()
+            .chain(&options.only_modules)
+            .chain(&options.exclude_modules);
+        for module_name in module_names {
+            if document.module(&witx::Id::new(module_name)).is_none() {
+                return Err(Error::Usage(format!("Unknown module [{}]", module_name)));
             }
-            witx::TypeRef::Value(type_) => type_.as_ref(),
         }
+        Ok(())
     }
 }
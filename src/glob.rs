@@ -0,0 +1,168 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Expand a list of paths and glob patterns into a list of files.
+///
+/// Patterns support `*`, `?` and `[...]` character classes within a path component.
+/// Matches of a pattern are sorted, duplicates are only included once, and
+/// the order of the input list is otherwise preserved.
+pub fn expand<S: AsRef<str>>(patterns: &[S]) -> Result<Vec<PathBuf>, io::Error> {
+    let mut paths: Vec<PathBuf> = vec![];
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        let matches = if is_pattern(pattern) {
+            let matches = expand_pattern(Path::new(pattern))?;
+            if matches.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No files match [{}]", pattern),
+                ));
+            }
+            matches
+        } else {
+            vec![PathBuf::from(pattern)]
+        };
+        for path in matches {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+fn expand_pattern(pattern: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut candidates = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let component_pattern = match component {
+            Component::Normal(x) => x.to_string_lossy(),
+            _ => {
+                for candidate in &mut candidates {
+                    candidate.push(component.as_os_str());
+                }
+                continue;
+            }
+        };
+        if !is_pattern(&component_pattern) {
+            for candidate in &mut candidates {
+                candidate.push(component.as_os_str());
+            }
+            continue;
+        }
+        let mut next_candidates = vec![];
+        for candidate in &candidates {
+            let dir = if candidate.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                candidate.as_path()
+            };
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                // Files matched by a pattern that isn't the last component
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound || !dir.is_dir() => continue,
+                Err(e) => return Err(e),
+            };
+            let mut names = vec![];
+            for entry in entries {
+                let name = entry?.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') && !component_pattern.starts_with('.') {
+                    continue;
+                }
+                if matches(component_pattern.as_bytes(), name.as_bytes()) {
+                    names.push(name);
+                }
+            }
+            names.sort();
+            next_candidates.extend(names.into_iter().map(|name| candidate.join(name)));
+        }
+        candidates = next_candidates;
+    }
+    Ok(candidates
+        .into_iter()
+        .filter(|candidate| candidate.exists())
+        .collect())
+}
+
+/// Match a name against a pattern
+pub fn is_match(pattern: &str, name: &str) -> bool {
+    matches(pattern.as_bytes(), name.as_bytes())
+}
+
+/// Match a single path component against a pattern.
+///
+/// After a mismatch, only the last `*` is extended, so that the time taken is
+/// proportional to the length of the pattern times the length of the name.
+fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Position in the pattern after the last `*`, and in the name where it stopped
+    let mut last_star = None;
+    while n < name.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            last_star = Some((p, n));
+            continue;
+        }
+        if let Some(len) = match_char(&pattern[p..], name[n]) {
+            p += len;
+            n += 1;
+            continue;
+        }
+        match last_star {
+            Some((star_p, star_n)) => {
+                p = star_p;
+                n = star_n + 1;
+                last_star = Some((star_p, n));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Match a character against the start of a pattern, returning the length of
+/// the pattern it was matched with
+fn match_char(pattern: &[u8], c: u8) -> Option<usize> {
+    match pattern.split_first() {
+        None => None,
+        Some((b'?', _)) => Some(1),
+        Some((b'[', rest)) => {
+            let negate = matches!(rest.first(), Some(b'!') | Some(b'^'));
+            let class_start = negate as usize;
+            // A `]` right after the opening bracket is part of the class
+            let end = match rest.iter().skip(class_start + 1).position(|&x| x == b']') {
+                None => return if c == b'[' { Some(1) } else { None },
+                Some(end) => class_start + 1 + end,
+            };
+            if class_matches(&rest[class_start..end], c) != negate {
+                Some(end + 2)
+            } else {
+                None
+            }
+        }
+        Some((&x, _)) if x == c => Some(1),
+        Some(_) => None,
+    }
+}
+
+fn class_matches(class: &[u8], c: u8) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            if class[i] <= c && c <= class[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
@@ -0,0 +1,112 @@
+//! Generate bindings and documentation from witx definitions.
+//!
+//! The `as-witx` command is a thin wrapper around this crate, which can also be
+//! used from a `build.rs` script:
+//!
+//! ```no_run
+//! let config = as_witx::Config {
+//!     target: as_witx::Target::Zig,
+//!     ..Default::default()
+//! };
+//! let code = as_witx::generate_from_paths(&["witx/wasi.witx"], &config).unwrap();
+//! std::fs::write("src/wasi.zig", code).unwrap();
+//! ```
+
+mod astype;
+pub mod backend;
+pub mod diff;
+pub mod error;
+pub mod generator;
+pub mod glob;
+pub mod pretty_writer;
+pub mod sources;
+
+pub use crate::backend::{Options, Target};
+pub use crate::error::Error;
+pub use crate::generator::{Generator, GeneratorOptions};
+pub use crate::sources::Sources;
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Everything that affects the generated code
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Output language
+    pub target: Target,
+    /// Options shared by all backends
+    pub options: Options,
+    /// Which modules and functions to generate, and the names they are imported from
+    pub generator: GeneratorOptions,
+}
+
+/// Generate code from witx files, which may `use` other files
+pub fn generate_from_paths<P: AsRef<Path>>(paths: &[P], config: &Config) -> Result<String, Error> {
+    let document = witx::load(paths)?;
+    generate_from_document(&document, config)
+}
+
+/// Generate code from witx source, which can't `use` other files
+pub fn generate_from_str(source: &str, config: &Config) -> Result<String, Error> {
+    let document = witx::parse(source)?;
+    generate_from_document(&document, config)
+}
+
+/// Generate code from witx files kept in memory, which may `use` each other
+pub fn generate_from_sources<P: AsRef<Path>>(
+    paths: &[P],
+    sources: &Sources,
+    config: &Config,
+) -> Result<String, Error> {
+    let document = sources::load_with(paths, sources)?;
+    generate_from_document(&document, config)
+}
+
+/// Generate code from a witx document that has already been loaded
+pub fn generate_from_document(document: &witx::Document, config: &Config) -> Result<String, Error> {
+    let backend = config.target.backend(&config.options);
+    let mut generator = Generator::new(vec![], backend, config.generator.clone());
+    generator.generate_document(document)?;
+    into_string(generator.into_inner())
+}
+
+/// Generate the types, each module and an index re-exporting them as separate files.
+///
+/// Files are returned as `(name, code)` pairs, with names not including an extension.
+pub fn generate_files_from_document(
+    document: &witx::Document,
+    config: &Config,
+) -> Result<Vec<(String, String)>, Error> {
+    let backend = config.target.backend(&config.options);
+    let mut generator = Generator::new(SharedBuffer::default(), backend, config.generator.clone());
+    let mut files = vec![];
+    generator.generate_document_split(document, |name| {
+        let buffer = SharedBuffer::default();
+        files.push((name.to_string(), buffer.clone()));
+        Ok(buffer)
+    })?;
+    files
+        .into_iter()
+        .map(|(name, buffer)| Ok((name, into_string(buffer.0.take())?)))
+        .collect()
+}
+
+fn into_string(output: Vec<u8>) -> Result<String, Error> {
+    String::from_utf8(output).map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// An output that can still be read after the generator has moved on to the next file
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,18 +1,28 @@
-mod astype;
-mod error;
-mod generator;
-mod pretty_writer;
-
 #[macro_use]
 extern crate clap;
 
-use crate::error::*;
-use crate::generator::*;
-use clap::Arg;
-use std::fs::File;
-use std::io::Write;
+use as_witx::error::EXIT_USAGE_ERROR;
+use as_witx::sources::{self, Sources, Tracker};
+use as_witx::{diff, glob, Config, Error, GeneratorOptions, Options, Target};
+use clap::{Arg, ErrorKind};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use witx::WitxIo;
 
 fn main() {
+    let mut sources = Sources::with_filesystem();
+    if let Err(e) = run(&mut sources) {
+        eprintln!("error: {}", e.report_with(&sources));
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(sources: &mut Sources) -> Result<(), Error> {
     let matches = app_from_crate!()
         .arg(
             Arg::with_name("module_name")
@@ -21,6 +31,64 @@ fn main() {
                 .value_name("module_name")
                 .help("Set the module name to use instead of reading it from the witx file"),
         )
+        .arg(
+            Arg::with_name("map_module")
+                .long("--map-module")
+                .value_name("old=new")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|x| match x.contains('=') {
+                    true => Ok(()),
+                    false => Err("Expected old=new".to_string()),
+                })
+                .help("Import the functions of a witx module from a different module name"),
+        )
+        .arg(
+            Arg::with_name("only_module")
+                .long("--only-module")
+                .value_name("module")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only generate this module"),
+        )
+        .arg(
+            Arg::with_name("exclude_module")
+                .long("--exclude-module")
+                .value_name("module")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Don't generate this module"),
+        )
+        .arg(
+            Arg::with_name("include_func")
+                .long("--include-func")
+                .value_name("pattern")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only generate the functions matching this pattern, and the types they use"),
+        )
+        .arg(
+            Arg::with_name("exclude_func")
+                .long("--exclude-func")
+                .value_name("pattern")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Don't generate the functions matching this pattern"),
+        )
+        .arg(
+            Arg::with_name("target")
+                .short("-t")
+                .long("--target")
+                .value_name("target")
+                .possible_values(Target::NAMES)
+                .default_value("assemblyscript")
+                .help("Output language"),
+        )
+        .arg(
+            Arg::with_name("wrappers")
+                .long("--wrappers")
+                .help("Also generate high-level wrappers around the imported functions"),
+        )
         .arg(
             Arg::with_name("output_file")
                 .short("-o")
@@ -30,19 +98,227 @@ fn main() {
                 .help("Output file, or - for the standard output"),
         )
         .arg(
-            Arg::with_name("witx_file")
-                .multiple(false)
+            Arg::with_name("out_dir")
+                .long("--out-dir")
+                .value_name("out_dir")
+                .conflicts_with("output_file")
+                .help(
+                    "Write the types, each module and an index to separate files in this directory",
+                ),
+        )
+        .arg(
+            Arg::with_name("check")
+                .long("--check")
+                .value_name("file")
+                .conflicts_with_all(&["output_file", "out_dir"])
+                .help(
+                    "Check that a file contains the generated code, printing a diff if it doesn't",
+                ),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("--watch")
+                .conflicts_with("check")
+                .help("Generate the output again every time the witx files change"),
+        )
+        .arg(
+            Arg::with_name("witx_files")
+                .multiple(true)
                 .required(true)
-                .help("wITX file"),
+                .help("wITX files, glob patterns matching wITX files, or - for the standard input"),
         )
-        .get_matches();
+        .get_matches_safe()
+        .unwrap_or_else(|e| match e.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => e.exit(),
+            // clap exits with 1 otherwise, which is the code for --check failures
+            _ => {
+                eprintln!("{}", e.message);
+                process::exit(EXIT_USAGE_ERROR);
+            }
+        });
 
-    let writer: Box<dyn Write> = match matches.value_of("output_file") {
-        None | Some("-") => Box::new(std::io::stdout()),
-        Some(file) => Box::new(File::create(file).unwrap()),
+    let witx_patterns: Vec<_> = matches.values_of("witx_files").unwrap().collect();
+    let witx_files = glob::expand(&witx_patterns)?;
+    if witx_files.iter().any(|x| x.as_os_str() == "-") {
+        if matches.is_present("watch") {
+            return Err(Error::Usage(
+                "--watch can't read from the standard input".to_string(),
+            ));
+        }
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|e| Error::File(PathBuf::from("-"), e))?;
+        sources.insert("-", source);
+    }
+    let values = |name| -> Vec<String> {
+        matches
+            .values_of(name)
+            .map_or(vec![], |values| values.map(|x| x.to_string()).collect())
+    };
+    let module_map = values("map_module")
+        .iter()
+        .filter_map(|x| x.split_once('='))
+        .map(|(old, new)| (old.to_string(), new.to_string()))
+        .collect();
+    let generator_options = GeneratorOptions {
+        module_name: matches.value_of("module_name").map(|x| x.to_string()),
+        module_map,
+        only_modules: values("only_module"),
+        exclude_modules: values("exclude_module"),
+        include_funcs: values("include_func"),
+        exclude_funcs: values("exclude_func"),
     };
-    let witx_file = matches.value_of("witx_file").unwrap();
-    let module_name = matches.value_of("module_name").map(|x| x.to_string());
-    let mut generator = Generator::new(writer, module_name);
-    generator.generate(witx_file).unwrap();
+    let config = Config {
+        target: matches.value_of("target").unwrap().parse().unwrap(),
+        options: Options {
+            wrappers: matches.is_present("wrappers"),
+        },
+        generator: generator_options,
+    };
+    let output = match (matches.value_of("output_file"), matches.value_of("out_dir")) {
+        (_, Some(out_dir)) => {
+            if config.target != Target::AssemblyScript {
+                return Err(Error::Usage(
+                    "--out-dir is only supported by the assemblyscript target".to_string(),
+                ));
+            }
+            Output::Dir(PathBuf::from(out_dir))
+        }
+        (None, None) | (Some("-"), None) => Output::Stdout,
+        (Some(file), None) => Output::File(PathBuf::from(file)),
+    };
+    if let Some(file) = matches.value_of("check") {
+        let output = as_witx::generate_from_sources(&witx_files, sources, &config)?;
+        let current = fs::read_to_string(file).map_err(|e| Error::File(PathBuf::from(file), e))?;
+        let generated = format!("{} (generated)", file);
+        if let Some(diff) = diff::unified_diff(&current, &output, file, &generated) {
+            print!("{}", diff);
+            return Err(Error::OutOfDate(PathBuf::from(file)));
+        }
+        return Ok(());
+    }
+    if matches.is_present("watch") {
+        if let Output::Stdout = output {
+            return Err(Error::Usage(
+                "--watch requires --output or --out-dir".to_string(),
+            ));
+        }
+        watch(&witx_files, sources, &config, &output);
+    }
+    generate(&witx_files, sources, &config, &output)
+}
+
+/// Where the generated code goes
+enum Output {
+    Stdout,
+    File(PathBuf),
+    Dir(PathBuf),
+}
+
+/// Generate code and write it to the output, leaving files that haven't changed untouched
+fn generate(
+    witx_files: &[PathBuf],
+    witxio: &dyn WitxIo,
+    config: &Config,
+    output: &Output,
+) -> Result<(), Error> {
+    let document = sources::load_with(witx_files, witxio)?;
+    match output {
+        Output::Stdout => {
+            let code = as_witx::generate_from_document(&document, config)?;
+            io::stdout().write_all(code.as_bytes())?;
+        }
+        Output::File(file) => {
+            let code = as_witx::generate_from_document(&document, config)?;
+            write_if_changed(file, &code)?;
+        }
+        Output::Dir(dir) => {
+            let files = as_witx::generate_files_from_document(&document, config)?;
+            fs::create_dir_all(dir).map_err(|e| Error::File(dir.clone(), e))?;
+            for (name, code) in files {
+                write_if_changed(&dir.join(format!("{}.ts", name)), &code)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_if_changed(file: &Path, contents: &str) -> Result<(), Error> {
+    if fs::read_to_string(file).is_ok_and(|current| current == contents) {
+        return Ok(());
+    }
+    write_atomically(file, contents).map_err(|e| Error::File(file.to_path_buf(), e))
+}
+
+/// Write a file through a temporary file renamed over it, so that it is
+/// never left truncated, even if writing fails halfway.
+fn write_atomically(file: &Path, contents: &str) -> Result<(), io::Error> {
+    let file_name = file
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file name"))?;
+    let tmp_file = file.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        process::id()
+    ));
+    let write = || -> Result<(), io::Error> {
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_file)?;
+        if let Ok(metadata) = fs::metadata(file) {
+            tmp.set_permissions(metadata.permissions())?;
+        }
+        tmp.write_all(contents.as_bytes())?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_file, file)
+    };
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&tmp_file);
+    })
+}
+
+/// How often to check whether the witx files have changed
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Generate code every time the witx files, or the files they `use`, change.
+///
+/// Errors are reported, and generation resumes after the next change.
+fn watch(witx_files: &[PathBuf], witxio: &dyn WitxIo, config: &Config, output: &Output) -> ! {
+    let modified = |path: &Path| fs::metadata(path).and_then(|x| x.modified()).ok();
+    let mut watched: Vec<(PathBuf, Option<SystemTime>)> = vec![];
+    loop {
+        let changed =
+            watched.is_empty() || watched.iter().any(|(path, mtime)| modified(path) != *mtime);
+        if changed {
+            // Files are checked before being read, so that changes made meanwhile aren't missed
+            let before: HashMap<_, _> = witx_files
+                .iter()
+                .chain(watched.iter().map(|(path, _)| path))
+                .map(|path| (path.clone(), modified(path)))
+                .collect();
+            let tracker = Tracker::new(witxio);
+            if let Err(e) = generate(witx_files, &tracker, config, output) {
+                eprintln!("error: {}", e.report_with(witxio));
+            }
+            let mut paths = witx_files.to_vec();
+            for path in tracker.into_paths() {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+            watched = paths
+                .into_iter()
+                .map(|path| {
+                    let mtime = before
+                        .get(&path)
+                        .copied()
+                        .unwrap_or_else(|| modified(&path));
+                    (path, mtime)
+                })
+                .collect();
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
 }
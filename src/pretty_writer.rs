@@ -40,6 +40,11 @@ impl<W: Write> PrettyWriter<W> {
         PrettyWriter::new_with_indent(writer, 0, indent_bytes)
     }
 
+    /// Return the underlying writer, unless other `PrettyWriter`s still share it
+    pub fn into_inner(self) -> Option<W> {
+        Rc::try_unwrap(self.writer).ok().map(RefCell::into_inner)
+    }
+
     /// Create a writer based on a existing writer, but with no indentation`
     #[allow(dead_code)]
     pub fn new_from_writer(&mut self) -> Self {
@@ -126,10 +131,8 @@ impl<W: Write> PrettyWriter<W> {
     /// Write multiple indented lines
     pub fn write_lines<T: AsRef<[u8]>>(&mut self, buf: T) -> Result<&mut Self, Error> {
         let buf = buf.as_ref();
-        for line in buf.lines() {
-            if let Ok(line) = line {
-                self.write_line(line)?;
-            }
+        for line in buf.lines().map_while(Result::ok) {
+            self.write_line(line)?;
         }
         Ok(self)
     }
@@ -0,0 +1,195 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use witx::parser::{TopLevelDocument, TopLevelSyntax};
+use witx::{DocValidation, WitxError, WitxIo};
+
+/// witx files kept in memory, used to resolve `use` declarations without the filesystem
+#[derive(Clone, Debug, Default)]
+pub struct Sources {
+    files: HashMap<PathBuf, String>,
+    filesystem: bool,
+}
+
+impl Sources {
+    /// Only files added with `insert()` can be loaded
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Files that haven't been added with `insert()` are read from the filesystem
+    pub fn with_filesystem() -> Self {
+        Sources {
+            files: HashMap::new(),
+            filesystem: true,
+        }
+    }
+
+    /// Add a file, replacing any previous file with the same path
+    pub fn insert<P: AsRef<Path>, S: Into<String>>(&mut self, path: P, source: S) -> &mut Self {
+        self.files.insert(normalize(path.as_ref()), source.into());
+        self
+    }
+
+    fn get(&self, path: &Path) -> Option<&String> {
+        self.files.get(&normalize(path))
+    }
+}
+
+impl WitxIo for Sources {
+    fn fgets(&self, path: &Path) -> Result<String, WitxError> {
+        match self.get(path) {
+            Some(source) => Ok(source.clone()),
+            None if self.filesystem => witx::Filesystem.fgets(path),
+            None => Err(WitxError::Io(
+                path.to_path_buf(),
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Not found in the in-memory sources",
+                ),
+            )),
+        }
+    }
+
+    fn fget_line(&self, path: &Path, line_num: usize) -> Result<String, WitxError> {
+        match self.get(path) {
+            Some(source) => source
+                .lines()
+                .nth(line_num - 1)
+                .map(|line| line.to_string())
+                .ok_or_else(|| {
+                    WitxError::Io(
+                        path.to_path_buf(),
+                        io::Error::new(io::ErrorKind::NotFound, "Line not found"),
+                    )
+                }),
+            None => witx::Filesystem.fget_line(path, line_num),
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, WitxError> {
+        match self.get(path) {
+            Some(_) => Ok(normalize(path)),
+            None if self.filesystem => witx::Filesystem.canonicalize(path),
+            None => Ok(normalize(path)),
+        }
+    }
+}
+
+/// Read files through another `WitxIo`, keeping track of the files that have been read
+pub struct Tracker<'a> {
+    witxio: &'a dyn WitxIo,
+    paths: RefCell<Vec<PathBuf>>,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(witxio: &'a dyn WitxIo) -> Self {
+        Tracker {
+            witxio,
+            paths: RefCell::new(vec![]),
+        }
+    }
+
+    /// The files that have been requested so far, including the files they `use`
+    pub fn into_paths(self) -> Vec<PathBuf> {
+        self.paths.into_inner()
+    }
+}
+
+impl WitxIo for Tracker<'_> {
+    fn fgets(&self, path: &Path) -> Result<String, WitxError> {
+        self.witxio.fgets(path)
+    }
+
+    fn fget_line(&self, path: &Path, line_num: usize) -> Result<String, WitxError> {
+        self.witxio.fget_line(path, line_num)
+    }
+
+    // Every file is canonicalized before being read, and files that don't exist yet are kept
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, WitxError> {
+        self.paths.borrow_mut().push(path.to_path_buf());
+        self.witxio.canonicalize(path)
+    }
+}
+
+/// Remove `.` components and resolve `..` components without accessing the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            _ => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Load a witx document, reading the files and the files they `use` through `witxio`
+pub fn load_with<P: AsRef<Path>>(
+    paths: &[P],
+    witxio: &dyn WitxIo,
+) -> Result<witx::Document, WitxError> {
+    let mut validator = DocValidation::new();
+    let mut definitions = vec![];
+    let mut parsed = HashSet::new();
+    for path in paths {
+        let path = path.as_ref();
+        let root = path.parent().unwrap_or_else(|| Path::new("."));
+        let file_name = path.file_name().map_or(path, Path::new);
+        parse_file(
+            file_name,
+            witxio,
+            root,
+            &mut validator,
+            &mut definitions,
+            &mut parsed,
+        )?;
+    }
+    Ok(validator.into_document(definitions))
+}
+
+fn parse_file(
+    path: &Path,
+    witxio: &dyn WitxIo,
+    root: &Path,
+    validator: &mut DocValidation,
+    definitions: &mut Vec<witx::Definition>,
+    parsed: &mut HashSet<PathBuf>,
+) -> Result<(), WitxError> {
+    let path = witxio.canonicalize(&root.join(path))?;
+    if !parsed.insert(path.clone()) {
+        return Ok(());
+    }
+    let input = witxio.fgets(&path)?;
+    let adjust_err = |mut e: wast::Error| {
+        e.set_path(&path);
+        e.set_text(&input);
+        WitxError::Parse(e)
+    };
+    let buf = wast::parser::ParseBuffer::new(&input).map_err(adjust_err)?;
+    let doc = wast::parser::parse::<TopLevelDocument>(&buf).map_err(adjust_err)?;
+    for item in doc.items {
+        match item.item {
+            TopLevelSyntax::Decl(decl) => validator
+                .scope(&input, &path)
+                .validate_decl(&decl, &item.comments, definitions)
+                .map_err(WitxError::Validation)?,
+            TopLevelSyntax::Use(used_path) => {
+                let root = path.parent().unwrap_or(root);
+                parse_file(
+                    Path::new(used_path),
+                    witxio,
+                    root,
+                    validator,
+                    definitions,
+                    parsed,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use as_witx::Config;
+
+fn generate(witx: &str) -> String {
+    as_witx::generate_from_str(witx, &Config::default()).unwrap()
+}
+
+#[test]
+fn enum_is_a_namespace_of_constants() {
+    let code = generate("(typename $errno (enum (@witx tag u16) $success $inval))");
+    assert!(code.contains("export type errno = u16;"));
+    assert!(code.contains("export namespace errno {"));
+    assert!(code.contains("    export const SUCCESS: errno = 0;"));
+    assert!(code.contains("    export const INVAL: errno = 1;"));
+    assert!(code.contains("            case INVAL: return \"inval\";"));
+}
+
+#[test]
+fn flags_are_a_namespace_of_bits() {
+    let code = generate("(typename $fl (flags (@witx repr u8) $read $write))");
+    assert!(code.contains("export type fl = u8;"));
+    assert!(code.contains("    export const READ: fl = 0x1;"));
+    assert!(code.contains("    export const WRITE: fl = 0x2;"));
+    assert!(code.contains("    export function has(flags: fl, flag: fl): bool {"));
+}
+
+#[test]
+fn bool_fields_are_32_bit() {
+    let code = generate(
+        "(typename $b bool)
+         (typename $rb (record (field $flag bool) (field $alias $b) (field $c u8)))",
+    );
+    assert!(code.contains("export type b = bool;"));
+    assert!(code.contains("    flag: u32;\n    alias: u32;\n    c: u8;\n"));
+    assert!(code.contains("if (offsetof<rb>() != 12) ERROR(\"rb: size\");"));
+    assert!(code.contains("if (offsetof<rb>(\"c\") != 8) ERROR(\"rb.c: offset\");"));
+}
+
+#[test]
+fn bool_payloads_are_32_bit() {
+    let code = generate("(typename $v (variant (case $b bool) (case $none)))");
+    assert!(code.contains("    static b(val: u32): v {"));
+    assert!(code.contains("    get_b(): u32 {"));
+}
+
+#[test]
+fn expected_is_a_variant() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $ex (expected u32 (error $errno)))",
+    );
+    assert!(code.contains("export class ex {\n    tag: u32;\n"));
+    assert!(code.contains("    static ok(val: u32): ex {"));
+    assert!(code.contains("    get_err(): errno {"));
+    assert!(code.contains("if (offsetof<ex>() != 8) ERROR(\"ex: size\");"));
+}
+
+#[test]
+fn expected_result_is_returned_through_a_pointer() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $size u32)
+         (module $m
+           (@interface func (export \"get\") (result $error (expected $size (error $errno)))))",
+    );
+    assert!(code.contains("@external(\"m\", \"get\")"));
+    assert!(code.contains("    result_ptr: mut_ptr<size>\n): errno /* error */;"));
+}
+
+#[test]
+fn tuple_members_are_numbered() {
+    let code = generate("(typename $tup (tuple u32 u64))");
+    assert!(code.contains("    _0: u32;\n    private __pad_4: u32;\n    _1: u64;\n"));
+    assert!(code.contains("if (offsetof<tup>(\"_1\") != 8) ERROR(\"tup._1: offset\");"));
+}
+
+#[test]
+fn lists_and_strings() {
+    let code = generate(
+        "(typename $str string)
+         (typename $names (list string))
+         (typename $mixed (record (field $s string) (field $l (list u32)) (field $n u64)))",
+    );
+    assert!(code.contains("export type str = WasiString;"));
+    assert!(code.contains("export type names = WasiArray<WasiString>;"));
+    assert!(code.contains("    private __s_0: u64;\n    private __l_8: u64;\n    n: u64;\n"));
+    assert!(code.contains("    get s(): WasiString {"));
+    assert!(code.contains("    get l(): WasiArray<u32> {"));
+    assert!(!code.contains("/* array */"));
+}
+
+#[test]
+fn pointers() {
+    let code = generate(
+        "(typename $cptr (@witx const_pointer u8))
+         (typename $mptr (@witx pointer u32))",
+    );
+    assert!(code.contains("export type cptr = ptr<u8>;"));
+    assert!(code.contains("export type mptr = mut_ptr<u32>;"));
+}
+
+#[test]
+fn handles() {
+    let code = generate(
+        "(typename $fd (handle))
+         (typename $r (record (field $fd $fd) (field $n u64)))",
+    );
+    assert!(code.contains("export type fd = handle;"));
+    assert!(code.contains("    fd: fd;\n    private __pad_4: u32;\n    n: u64;\n"));
+}
+
+#[test]
+fn variant_with_payload() {
+    let code = generate(
+        "(typename $v (variant (@witx tag u8) (case $s string) (case $n u32) (case $none)))",
+    );
+    assert!(code.contains("export class v {\n    tag: u8;\n"));
+    assert!(code.contains("    // --- s: WasiString if tag=0"));
+    assert!(code.contains("    static s(val: WasiString): v {"));
+    assert!(code.contains("    get_s(): WasiString | null {"));
+    assert!(code.contains("    get_n(): u32 {"));
+    assert!(code.contains("    static none(): v {"));
+    assert!(code.contains("if (offsetof<v>() != 12) ERROR(\"v: size\");"));
+    assert!(!code.contains("/* array */"));
+}
+
+#[test]
+fn wrappers_decode_tuple_results() {
+    let config = Config {
+        options: as_witx::Options { wrappers: true },
+        ..Default::default()
+    };
+    let code = as_witx::generate_from_str(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $size u32)
+         (typename $str string)
+         (module $m
+           (@interface func (export \"sizes_get\")
+             (result $error (expected (tuple $size $str) (error $errno)))))",
+        &config,
+    )
+    .unwrap();
+    assert!(code.contains("export class sizes_get_results {\n    _0: size;\n    _1: string;\n"));
+    assert!(code.contains("export function trySizesGet(): Result<sizes_get_results, errno> {"));
+    assert!(code.contains(
+        "    let result = new sizes_get_results(load<size>(result_ptr + 0), \
+         changetype<WasiString>(result_ptr + 4).toString());\n    heap.free(result_ptr);\n"
+    ));
+    assert!(code.contains("export function sizesGet(): sizes_get_results {"));
+}
+
+#[test]
+fn anonymous_payloads_are_accessed_by_address() {
+    let code = generate("(typename $v (variant (case $t (tuple u8 u64)) (case $none)))");
+    assert!(code.contains(
+        "    static t(val: usize): v {\n        let tu = new v(0);\n        \
+         memory.copy(changetype<usize>(tu) + 8, val, 16);\n"
+    ));
+    assert!(code
+        .contains("        memory.fill(valBuf, 0, 16);\n        memory.copy(valBuf, val, 16);\n"));
+    assert!(
+        code.contains("    get_t(): usize {\n        return changetype<usize>(this) + 8;\n    }\n")
+    );
+    assert!(!code.contains("get<usize>"));
+}
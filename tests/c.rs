@@ -0,0 +1,38 @@
+use as_witx::{Config, Target};
+
+fn generate(witx: &str) -> String {
+    let config = Config {
+        target: Target::C,
+        ..Default::default()
+    };
+    as_witx::generate_from_str(witx, &config).unwrap()
+}
+
+#[test]
+fn bool_is_32_bit() {
+    let code = generate(
+        "(typename $b bool)
+         (typename $rb (record (field $flag bool) (field $c u8)))",
+    );
+    assert!(code.contains("typedef uint32_t witx_b_t;"));
+    assert!(code.contains("    uint32_t flag;\n"));
+    assert!(code.contains("_Static_assert(offsetof(witx_rb_t, c) == 4"));
+    assert!(code.contains("_Static_assert(sizeof(witx_rb_t) == 8"));
+}
+
+#[test]
+fn anonymous_variants_have_a_payload() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $r
+           (record
+             (field $e (expected u32 (error $errno)))
+             (field $v (variant (case $x u64) (case $y)))
+             (field $b u8)))",
+    );
+    assert!(code
+        .contains("    struct { uint32_t tag; union { uint32_t ok; witx_errno_t err; } u; } e;\n"));
+    assert!(code.contains("    struct { uint32_t tag; union { uint64_t x; } u; } v;\n"));
+    assert!(code.contains("_Static_assert(offsetof(witx_r_t, v) == 8"));
+    assert!(code.contains("_Static_assert(offsetof(witx_r_t, b) == 24"));
+}
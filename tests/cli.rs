@@ -0,0 +1,23 @@
+use std::process::Command;
+
+fn exit_code(args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_as-witx"))
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+fn invalid_options_are_usage_errors() {
+    assert_eq!(exit_code(&["--target", "foo", "x.witx"]), Some(64));
+    assert_eq!(exit_code(&["--bogus", "x.witx"]), Some(64));
+    assert_eq!(exit_code(&[]), Some(64));
+}
+
+#[test]
+fn help_and_version_succeed() {
+    assert_eq!(exit_code(&["--help"]), Some(0));
+    assert_eq!(exit_code(&["--version"]), Some(0));
+}
@@ -0,0 +1,162 @@
+use as_witx::diff::unified_diff;
+
+fn diff(old: &str, new: &str) -> String {
+    unified_diff(old, new, "a", "b").unwrap()
+}
+
+fn lines(n: usize) -> String {
+    (1..=n).map(|i| format!("{}\n", i)).collect()
+}
+
+/// Apply a unified diff to `old`, checking that context and removed lines match
+fn patch(old: &str, diff: &str) -> String {
+    let old_lines: Vec<_> = old.split_inclusive('\n').collect();
+    let mut new = String::new();
+    let mut i = 0;
+    let mut diff_lines = diff.lines().skip(2).peekable();
+    while let Some(header) = diff_lines.next() {
+        let old_range = header.split(' ').nth(1).unwrap().trim_start_matches('-');
+        let start: usize = old_range.split(',').next().unwrap().parse().unwrap();
+        let start = if old_range.ends_with(",0") {
+            start
+        } else {
+            start - 1
+        };
+        while i < start {
+            new += old_lines[i];
+            i += 1;
+        }
+        while let Some(line) = diff_lines.next_if(|line| !line.starts_with("@@")) {
+            let no_newline = diff_lines
+                .next_if_eq(&"\\ No newline at end of file")
+                .is_some();
+            let text = if no_newline {
+                line[1..].to_string()
+            } else {
+                format!("{}\n", &line[1..])
+            };
+            match &line[..1] {
+                " " | "-" => {
+                    assert_eq!(old_lines[i], text);
+                    i += 1;
+                    if line.starts_with(' ') {
+                        new += &text;
+                    }
+                }
+                "+" => new += &text,
+                _ => panic!("unexpected line: {}", line),
+            }
+        }
+    }
+    for line in &old_lines[i..] {
+        new += line;
+    }
+    new
+}
+
+/// Number of lines in the longest common subsequence of two texts
+fn lcs(old: &str, new: &str) -> usize {
+    let a: Vec<_> = old.split_inclusive('\n').collect();
+    let b: Vec<_> = new.split_inclusive('\n').collect();
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if a[i] == b[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+    table[0][0]
+}
+
+#[test]
+fn identical_texts_have_no_diff() {
+    assert_eq!(unified_diff("a\nb\n", "a\nb\n", "a", "b"), None);
+}
+
+#[test]
+fn hunk_header_ranges() {
+    let old = lines(20);
+    let new = old.replace("10\n", "ten\n");
+    assert_eq!(
+        diff(&old, &new),
+        "--- a\n+++ b\n@@ -7,7 +7,7 @@\n 7\n 8\n 9\n-10\n+ten\n 11\n 12\n 13\n"
+    );
+
+    // A single line has no count, and an empty range starts at the line before it
+    assert_eq!(diff("a\n", "b\n"), "--- a\n+++ b\n@@ -1 +1 @@\n-a\n+b\n");
+    assert_eq!(diff("", "a\n"), "--- a\n+++ b\n@@ -0,0 +1 @@\n+a\n");
+    assert_eq!(
+        diff(&lines(2), "1\nnew\n2\n"),
+        "--- a\n+++ b\n@@ -1,2 +1,3 @@\n 1\n+new\n 2\n"
+    );
+}
+
+#[test]
+fn distant_changes_are_separate_hunks() {
+    let old = lines(30);
+    let new = old
+        .replacen("2\n", "two\n", 1)
+        .replace("29\n", "twenty-nine\n");
+    let diff = diff(&old, &new);
+    let headers: Vec<_> = diff.lines().filter(|line| line.starts_with("@@")).collect();
+    assert_eq!(headers, ["@@ -1,5 +1,5 @@", "@@ -26,5 +26,5 @@"]);
+}
+
+#[test]
+fn missing_newline_at_end_of_file() {
+    assert_eq!(
+        diff("a\nb\n", "a\nb"),
+        "--- a\n+++ b\n@@ -1,2 +1,2 @@\n a\n-b\n+b\n\\ No newline at end of file\n"
+    );
+    assert_eq!(
+        diff("a", "b"),
+        "--- a\n+++ b\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n\
+         +b\n\\ No newline at end of file\n"
+    );
+}
+
+#[test]
+fn diffs_are_minimal_and_apply() {
+    // Small alphabets produce many repeated lines, which is where diffs go wrong
+    let mut seed = 0x2545_f491_u32;
+    let mut random = move |n: u32| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed % n
+    };
+    for _ in 0..2000 {
+        let mut text = || -> String {
+            let len = random(12);
+            (0..len).map(|_| format!("{}\n", random(4))).collect()
+        };
+        let (old, new) = (text(), text());
+        let diff = match unified_diff(&old, &new, "a", "b") {
+            None => {
+                assert_eq!(old, new);
+                continue;
+            }
+            Some(diff) => diff,
+        };
+        assert_eq!(patch(&old, &diff), new, "{}", diff);
+        let changes = diff
+            .lines()
+            .skip(2)
+            .filter(|line| line.starts_with('-') || line.starts_with('+'))
+            .count();
+        let expected = old.lines().count() + new.lines().count() - 2 * lcs(&old, &new);
+        assert_eq!(changes, expected, "{}", diff);
+    }
+}
+
+#[test]
+fn large_different_texts() {
+    let old: String = (0..20_000).map(|i| format!("old {}\n", i)).collect();
+    let new: String = (0..20_000).map(|i| format!("new {}\n", i)).collect();
+    let diff = diff(&old, &new);
+    assert!(diff.starts_with("--- a\n+++ b\n@@ -1,20000 +1,20000 @@\n-old 0\n"));
+    assert_eq!(patch(&old, &diff), new);
+}
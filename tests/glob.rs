@@ -0,0 +1,103 @@
+use as_witx::glob::{expand, is_match};
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn literal_and_question_mark() {
+    assert!(is_match("fd_read", "fd_read"));
+    assert!(!is_match("fd_read", "fd_reads"));
+    assert!(!is_match("fd_read", "fd_rea"));
+    assert!(is_match("fd_?ead", "fd_read"));
+    assert!(!is_match("fd_?ead", "fd_ead"));
+    assert!(is_match("", ""));
+    assert!(!is_match("", "a"));
+}
+
+#[test]
+fn star() {
+    assert!(is_match("*", ""));
+    assert!(is_match("*", "fd_read"));
+    assert!(is_match("fd_*", "fd_"));
+    assert!(is_match("fd_*", "fd_read"));
+    assert!(!is_match("fd_*", "path_open"));
+    assert!(is_match("*_get", "args_sizes_get"));
+    assert!(is_match("**", "abc"));
+}
+
+#[test]
+fn star_backtracking() {
+    // The first `_` isn't the right one for the rest of the pattern to match
+    assert!(is_match("*_sizes_get", "args_sizes_sizes_get"));
+    assert!(is_match("a*b*c", "aXbXbXc"));
+    assert!(!is_match("a*b*c", "aXbXbX"));
+    assert!(is_match("*a?", "aaab"));
+    assert!(!is_match("*a?", "aaba"));
+    assert!(is_match("*[0-9]", "clock9"));
+
+    // Would take exponential time if every `*` was backtracked into
+    let name = "a".repeat(100);
+    assert!(!is_match("*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*a*b", &name));
+}
+
+#[test]
+fn classes() {
+    assert!(is_match("fd_[rw]*", "fd_read"));
+    assert!(is_match("fd_[rw]*", "fd_write"));
+    assert!(!is_match("fd_[rw]*", "fd_close"));
+    assert!(is_match("v[0-9]", "v7"));
+    assert!(!is_match("v[0-9]", "vx"));
+    assert!(is_match("[a-cx-z]", "y"));
+    assert!(!is_match("[a-cx-z]", "m"));
+    assert!(is_match("[-a]", "-"));
+    assert!(is_match("[a-]", "-"));
+}
+
+#[test]
+fn negated_classes() {
+    assert!(is_match("[!a]", "b"));
+    assert!(!is_match("[!a]", "a"));
+    assert!(is_match("[^a-c]", "d"));
+    assert!(!is_match("[^a-c]", "b"));
+    assert!(!is_match("[!a]", ""));
+}
+
+#[test]
+fn brackets() {
+    // A `]` right after the opening bracket, negated or not, is part of the class
+    assert!(is_match("[]]", "]"));
+    assert!(is_match("[!]]", "a"));
+    assert!(!is_match("[!]]", "]"));
+    // An unclosed bracket is a literal
+    assert!(is_match("a[b", "a[b"));
+    assert!(!is_match("a[b", "ab"));
+}
+
+#[test]
+fn expand_patterns() {
+    let dir = std::env::temp_dir().join(format!("as-witx-glob-{}", std::process::id()));
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    for name in &["b.witx", "a.witx", "c.txt", ".hidden.witx", "sub/d.witx"] {
+        fs::write(dir.join(name), "").unwrap();
+    }
+    let prefix = dir.to_str().unwrap();
+
+    let paths = expand(&[format!("{}/*.witx", prefix)]).unwrap();
+    assert_eq!(paths, [dir.join("a.witx"), dir.join("b.witx")]);
+
+    // Matches are sorted, and not repeated
+    let paths = expand(&[
+        format!("{}/b.witx", prefix),
+        format!("{}/*/*.witx", prefix),
+        format!("{}/?.witx", prefix),
+    ])
+    .unwrap();
+    let expected: Vec<PathBuf> = vec![
+        dir.join("b.witx"),
+        dir.join("sub/d.witx"),
+        dir.join("a.witx"),
+    ];
+    assert_eq!(paths, expected);
+
+    assert!(expand(&[format!("{}/*.nothing", prefix)]).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
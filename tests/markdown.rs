@@ -0,0 +1,84 @@
+use as_witx::{Config, Target};
+
+fn generate(witx: &str) -> String {
+    let config = Config {
+        target: Target::Markdown,
+        ..Default::default()
+    };
+    as_witx::generate_from_str(witx, &config).unwrap()
+}
+
+#[test]
+fn records_list_member_offsets() {
+    let code = generate(
+        "(typename $r
+           (record
+             ;;; First member
+             (field $a u8)
+             (field $n u64)))",
+    );
+    assert!(code.contains("## `r`\n\nSize: 16 bytes, alignment: 8 bytes\n\nRecord.\n"));
+    assert!(code.contains(
+        "| Offset | Member | Type | Description |\n\
+         | ------ | ------ | ---- | ----------- |\n\
+         | 0 | `a` | `u8` | First member |\n\
+         | 8 | `n` | `u64` |  |\n"
+    ));
+}
+
+#[test]
+fn enums_list_case_values() {
+    let code = generate(
+        "(typename $errno
+           (enum (@witx tag u16)
+             ;;; No error
+             $success
+             $inval))",
+    );
+    assert!(code.contains("Size: 2 bytes, alignment: 2 bytes\n\nEnumeration, stored as `u16`.\n"));
+    assert!(code.contains(
+        "| Value | Case | Description |\n\
+         | ----- | ---- | ----------- |\n\
+         | 0 | `success` | No error |\n\
+         | 1 | `inval` |  |\n"
+    ));
+}
+
+#[test]
+fn flags_list_bits() {
+    let code = generate("(typename $fl (flags (@witx repr u8) $read $write))");
+    assert!(code.contains("Size: 1 byte, alignment: 1 byte\n\nFlags, stored as `u8`.\n"));
+    assert!(code.contains(
+        "| Bit | Flag | Description |\n\
+         | --- | ---- | ----------- |\n\
+         | `1 << 0` | `read` |  |\n\
+         | `1 << 1` | `write` |  |\n"
+    ));
+}
+
+#[test]
+fn functions_list_params_and_results() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $size u32)
+         (module $m
+           ;;; Get a value
+           (@interface func (export \"get\")
+             ;;; Where to get it | from
+             (param $path string)
+             (result $error (expected $size (error $errno)))))",
+    );
+    assert!(code.contains("# Module `m`\n\n## `get`\n\nGet a value\n\n"));
+    assert!(code.contains(
+        "Parameters:\n\n\
+         | Name | Type | Description |\n\
+         | ---- | ---- | ----------- |\n\
+         | `path` | `string` | Where to get it \\| from |\n\n"
+    ));
+    assert!(code.contains(
+        "Results:\n\n\
+         | Name | Type | Description |\n\
+         | ---- | ---- | ----------- |\n\
+         | `error` | expected<[`size`](#size), [`errno`](#errno)> |  |\n"
+    ));
+}
@@ -0,0 +1,84 @@
+use as_witx::{Config, Target};
+
+fn generate(witx: &str) -> String {
+    let config = Config {
+        target: Target::Rust,
+        ..Default::default()
+    };
+    as_witx::generate_from_str(witx, &config).unwrap()
+}
+
+#[test]
+fn lists_are_ffi_safe() {
+    let code = generate(
+        "(typename $mixed (record (field $s string) (field $l (list u32))))
+         (typename $names (list string))",
+    );
+    assert!(code.contains("pub struct WasiList<T> {"));
+    assert!(code.contains("    pub s: WasiList<u8>,\n    pub l: WasiList<u32>,\n"));
+    assert!(code.contains("pub type Names = WasiList<WasiList<u8>>;"));
+    assert!(!code.contains("&'"));
+}
+
+#[test]
+fn enums_are_integer_newtypes() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (module $m
+           (@interface func (export \"f\") (result $error (expected (error $errno)))))",
+    );
+    assert!(code.contains("#[repr(transparent)]"));
+    assert!(code.contains("pub struct Errno(pub u16);"));
+    assert!(code.contains("    pub const SUCCESS: Errno = Errno(0);\n"));
+    assert!(code.contains("    pub const INVAL: Errno = Errno(1);\n"));
+    assert!(code.contains("pub fn f() -> Errno;"));
+}
+
+#[test]
+fn chars_and_bools_are_integers() {
+    let code = generate(
+        "(typename $c char)
+         (typename $rb (record (field $flag bool) (field $c u8)))",
+    );
+    assert!(code.contains("pub type C = u32;"));
+    assert!(code.contains("    pub flag: u32,\n    pub c: u8,\n"));
+}
+
+#[test]
+fn constants_outside_of_an_enum_are_kept() {
+    let code = generate(
+        "(typename $clockid (enum (@witx tag u32) $realtime $monotonic))
+         (@witx const $clockid $mono 1)
+         (@witx const $clockid $extra 7)
+         (typename $size u32)",
+    );
+    assert!(code.contains("pub const CLOCKID_MONO: Clockid = Clockid::MONOTONIC;"));
+    assert!(code.contains("pub const CLOCKID_EXTRA: Clockid = Clockid(7);"));
+}
+
+#[test]
+fn anonymous_types_are_named_after_their_use() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $r
+           (record
+             (field $a (tuple u32 u8))
+             (field $e (expected u32 (error $errno)))
+             (field $v (variant (case $x u64) (case $y)))))
+         (typename $t (variant (case $t (tuple u8 u64)) (case $n)))
+         (module $m
+           (@interface func (export \"get\") (param $p (tuple u8 u64))
+             (result $error (expected $r (error $errno)))))",
+    );
+    assert!(code.contains("    pub a: RA,\n    pub e: RE,\n    pub v: RV,\n"));
+    assert!(
+        code.contains("#[repr(C)]\n#[derive(Clone, Copy, Debug)]\npub struct RA(pub u32, pub u8);")
+    );
+    assert!(code.contains("#[repr(C, u32)]\n#[derive(Clone, Copy, Debug)]\npub enum RE {\n    Ok(u32),\n    Err(Errno),\n}"));
+    assert!(code.contains("pub enum RV {\n    X(u64),\n    Y,\n}"));
+    assert!(code.contains("    T(TT),\n"));
+    assert!(code.contains("pub struct TT(pub u8, pub u64);"));
+    assert!(code.contains("pub fn get(p: *const GetP, result_ptr: *mut R) -> Errno;"));
+    assert!(code.contains("pub struct GetP(pub u8, pub u64);"));
+    assert!(!code.contains(": (") && !code.contains("*const ("));
+}
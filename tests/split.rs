@@ -0,0 +1,40 @@
+use as_witx::{Config, Error};
+
+fn generate_files(witx: &str) -> Result<Vec<(String, String)>, Error> {
+    let document = witx::parse(witx).unwrap();
+    as_witx::generate_files_from_document(&document, &Config::default())
+}
+
+#[test]
+fn modules_get_their_own_file() {
+    let files = generate_files(
+        "(typename $size u32)
+         (module $wasi (@interface func (export \"f\") (param $n $size)))",
+    )
+    .unwrap();
+    let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["types", "wasi", "index"]);
+    assert!(files[2].1.contains("export * from \"./wasi\";"));
+}
+
+#[test]
+fn modules_named_after_reserved_files_are_rejected() {
+    for module_name in &["types", "index", "Index"] {
+        let witx = format!("(module ${} (@interface func (export \"f\")))", module_name);
+        match generate_files(&witx) {
+            Err(Error::Usage(message)) => assert!(message.contains(module_name)),
+            other => panic!("{}: unexpected result {:?}", module_name, other),
+        }
+    }
+}
+
+#[test]
+fn modules_that_are_not_a_file_name_are_rejected() {
+    for module_name in &["../escaped", "sub/m", "sub\\m", ".hidden", "..", "."] {
+        let witx = format!("(module ${} (@interface func (export \"f\")))", module_name);
+        match generate_files(&witx) {
+            Err(Error::Usage(message)) => assert!(message.contains("isn't a file name")),
+            other => panic!("{}: unexpected result {:?}", module_name, other),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use as_witx::{Config, Target};
+
+fn generate(witx: &str) -> String {
+    let config = Config {
+        target: Target::Zig,
+        ..Default::default()
+    };
+    as_witx::generate_from_str(witx, &config).unwrap()
+}
+
+#[test]
+fn lists_in_extern_types_are_pointer_and_length() {
+    let code = generate(
+        "(typename $mixed (record (field $s string) (field $l (list u32)) (field $n u64)))
+         (typename $v (variant (case $s string) (case $n u32)))",
+    );
+    assert!(code.contains("    s: extern struct { ptr: [*]const u8, len: usize },\n"));
+    assert!(code.contains("    l: extern struct { ptr: [*]const u32, len: usize },\n"));
+    assert!(code.contains("        s: extern struct { ptr: [*]const u8, len: usize },\n"));
+    assert!(!code.contains("[]const"));
+}
+
+#[test]
+fn wrappers_take_slices() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (module $m
+           (@interface func (export \"write\") (param $s string)
+             (result $error (expected (error $errno)))))",
+    );
+    assert!(code.contains("pub extern \"m\" fn write(s_ptr: [*]const u8, s_len: usize) errno_t;"));
+    assert!(code.contains("pub fn writeChecked(s: []const u8) errno_error!void {"));
+}
+
+#[test]
+fn bool_is_32_bit() {
+    let code = generate(
+        "(typename $b bool)
+         (typename $rb (record (field $flag bool) (field $c u8)))",
+    );
+    assert!(code.contains("pub const b_t = u32;"));
+    assert!(code.contains("    flag: u32,\n    c: u8,\n"));
+}
+
+#[test]
+fn constants_outside_of_an_enum_are_kept() {
+    let code = generate(
+        "(typename $clockid (enum (@witx tag u32) $realtime $monotonic))
+         (@witx const $clockid $mono 1)
+         (@witx const $clockid $extra 7)
+         (typename $size u32)",
+    );
+    assert!(code.contains("pub const CLOCKID_MONO: clockid_t = .monotonic;"));
+    assert!(code.contains("pub const CLOCKID_EXTRA: clockid_t = @enumFromInt(7);"));
+}
+
+#[test]
+fn tuple_results_are_a_named_struct() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $size u32)
+         (typename $str string)
+         (module $m
+           (@interface func (export \"get\")
+             (result $error (expected (tuple $size $str) (error $errno)))))",
+    );
+    assert!(
+        code.contains("pub const get_results = extern struct { @\"0\": size_t, @\"1\": str_t };\n")
+    );
+    assert!(code.contains("pub fn getChecked() errno_error!get_results {"));
+    assert!(code.contains("    var result: get_results = undefined;\n"));
+    assert!(code.contains("    try errno_check(get(&result.@\"0\", &result.@\"1\"));\n"));
+}
+
+#[test]
+fn enums_are_non_exhaustive() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (typename $v (variant (case $s u32) (case $n)))
+         (module $m
+           (@interface func (export \"f\") (result $error (expected (error $errno)))))",
+    );
+    assert!(code.contains("pub const errno_t = enum(u16) {\n    success,\n    inval,\n    _,\n};"));
+    assert!(code
+        .contains("    pub const Tag = enum(u32) {\n        s,\n        n,\n        _,\n    };"));
+    assert!(code.contains("pub const errno_error = error{ inval, Unexpected };"));
+    assert!(code.contains("        .inval => error.inval,\n        else => error.Unexpected,\n"));
+}
+
+#[test]
+fn anonymous_params_are_named_types() {
+    let code = generate(
+        "(typename $errno (enum (@witx tag u16) $success $inval))
+         (module $m
+           (@interface func (export \"get\")
+             (param $l (list (tuple u8 u32)))
+             (param $p (tuple u8 u64))
+             (result $error (expected (error $errno)))))",
+    );
+    assert!(code.contains("pub const get_l_element = extern struct { @\"0\": u8, @\"1\": u32 };"));
+    assert!(code.contains("pub const get_p = extern struct { @\"0\": u8, @\"1\": u64 };"));
+    assert!(code.contains(
+        "pub extern \"m\" fn get(l_ptr: [*]const get_l_element, l_len: usize, p: *const get_p) errno_t;"
+    ));
+    assert!(code.contains(
+        "pub fn getChecked(l: []const get_l_element, p: *const get_p) errno_error!void {"
+    ));
+}